@@ -0,0 +1,169 @@
+//the game never asks for keys directly, it asks for actions and the bindings decide which keys trigger them
+
+use three::Key;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    ToggleAutoFire,
+    ToggleOrbit,
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    //doubles as back in the menus
+    Pause,
+    Confirm
+}
+
+pub const ACTIONS: [Action; 13] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Fire,
+    Action::ToggleAutoFire,
+    Action::ToggleOrbit,
+    Action::Weapon1,
+    Action::Weapon2,
+    Action::Weapon3,
+    Action::Weapon4,
+    Action::Pause,
+    Action::Confirm
+];
+
+//every key that can be bound, they are named after their variant in the config file
+pub const KEYS: [Key; 56] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9, Key::Key0,
+    Key::Up, Key::Down, Key::Left, Key::Right,
+    Key::Space, Key::Return, Key::Escape, Key::Tab, Key::Back,
+    Key::LShift, Key::RShift, Key::LControl, Key::RControl, Key::LAlt, Key::RAlt,
+    Key::Comma, Key::Period, Key::Slash, Key::Semicolon, Key::Minus
+];
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Binding {
+    pub action: Action,
+    pub keys: Vec<Key>
+}
+
+//the actions of a single frame, pressed only holds actions that started this frame
+//the keyboard fills these in first, a gamepad adds its own actions on top
+#[derive(Clone, PartialEq, Debug)]
+pub struct Controls {
+    pub pressed: Vec<Action>,
+    pub held: Vec<Action>,
+    //sideways and upwards movement, each between -1.0 and 1.0
+    pub movement: [f32; 2]
+}
+
+impl Controls {
+    pub fn pressed(&self, action: Action) -> bool {
+        return self.pressed.contains(&action);
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        return self.held.contains(&action);
+    }
+}
+
+pub fn name(action: Action) -> &'static str {
+    match action {
+        Action::MoveUp => return "move_up",
+        Action::MoveDown => return "move_down",
+        Action::MoveLeft => return "move_left",
+        Action::MoveRight => return "move_right",
+        Action::Fire => return "fire",
+        Action::ToggleAutoFire => return "toggle_auto_fire",
+        Action::ToggleOrbit => return "toggle_orbit",
+        Action::Weapon1 => return "weapon_1",
+        Action::Weapon2 => return "weapon_2",
+        Action::Weapon3 => return "weapon_3",
+        Action::Weapon4 => return "weapon_4",
+        Action::Pause => return "pause",
+        Action::Confirm => return "confirm"
+    }
+}
+
+pub fn from_name(name: &str) -> Option<Action> {
+    return ACTIONS.iter().cloned().find(|action| self::name(*action) == name);
+}
+
+pub fn key_name(key: Key) -> String {
+    return format!("{:?}", key);
+}
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    return KEYS.iter().cloned().find(|key| key_name(*key) == name);
+}
+
+pub fn default_keys(action: Action) -> Vec<Key> {
+    match action {
+        Action::MoveUp => return vec![Key::W, Key::Up],
+        Action::MoveDown => return vec![Key::S, Key::Down],
+        Action::MoveLeft => return vec![Key::A, Key::Left],
+        Action::MoveRight => return vec![Key::D, Key::Right],
+        Action::Fire => return vec![Key::Space],
+        Action::ToggleAutoFire => return vec![Key::F],
+        Action::ToggleOrbit => return vec![Key::Tab],
+        Action::Weapon1 => return vec![Key::Key1],
+        Action::Weapon2 => return vec![Key::Key2],
+        Action::Weapon3 => return vec![Key::Key3],
+        Action::Weapon4 => return vec![Key::Key4],
+        Action::Pause => return vec![Key::Escape],
+        Action::Confirm => return vec![Key::Return]
+    }
+}
+
+pub fn default_bindings() -> Vec<Binding> {
+    return ACTIONS.iter().map(|action| Binding{ action: *action, keys: default_keys(*action) }).collect();
+}
+
+//a key counts as pressed on the frame it went down, holding it doesn't press it again
+//the same single press check the fire button has always used
+fn key_pressed(window: &three::Window, key: Key) -> bool {
+    return window.input.hit(key) && window.input.hit_count(three::controls::Button::Key(key)) == 1;
+}
+
+//the first bindable key that went down this frame, used when the player picks a new key for an action
+pub fn pressed_key(window: &three::Window) -> Option<Key> {
+    return KEYS.iter().cloned().find(|key| key_pressed(window, *key));
+}
+
+pub fn read(window: &three::Window, bindings: &Vec<Binding>) -> Controls {
+    let mut controls = Controls {
+        pressed: Vec::new(),
+        held: Vec::new(),
+        movement: [0.0, 0.0]
+    };
+
+    for binding in bindings.iter() {
+        if binding.keys.iter().any(|key| key_pressed(window, *key)) {
+            controls.pressed.push(binding.action);
+        }
+        if binding.keys.iter().any(|key| window.input.hit(*key)) {
+            controls.held.push(binding.action);
+        }
+    }
+
+    let axis = |positive: Action, negative: Action| -> f32 {
+        let mut value = 0.0;
+        if controls.held(positive) {
+            value = value + 1.0;
+        }
+        if controls.held(negative) {
+            value = value - 1.0;
+        }
+        return value;
+    };
+    let movement = [axis(Action::MoveRight, Action::MoveLeft), axis(Action::MoveUp, Action::MoveDown)];
+    controls.movement = movement;
+
+    return controls;
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+//enemy archetypes are plain data, enemy_spawn picks one based on how strong the detected onset was
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Archetype {
+    pub hit_points: i32,
+    pub size: f32,
+    pub velocity: f32,
+    pub color: u32,
+    pub points: i32,
+    //fires a projectile at the player every this many beats
+    pub shoots_every: Option<i32>,
+    //when destroyed the enemy breaks up into this many smaller enemies
+    pub splits_into: Option<(&'static Archetype, i32)>
+}
+
+pub const DRONE: Archetype = Archetype {
+    hit_points: 1,
+    size: 1.0,
+    velocity: 0.07,
+    color: 0xFF0000,
+    points: 100,
+    shoots_every: None,
+    splits_into: None
+};
+
+pub const DART: Archetype = Archetype {
+    hit_points: 1,
+    size: 0.6,
+    velocity: 0.14,
+    color: 0xFFFF00,
+    points: 150,
+    shoots_every: None,
+    splits_into: None
+};
+
+pub const ARMORED: Archetype = Archetype {
+    hit_points: 3,
+    size: 1.4,
+    velocity: 0.05,
+    color: 0x808080,
+    points: 250,
+    shoots_every: Some(4),
+    splits_into: None
+};
+
+pub const GUNNER: Archetype = Archetype {
+    hit_points: 2,
+    size: 1.0,
+    velocity: 0.04,
+    color: 0x00CED1,
+    points: 200,
+    shoots_every: Some(2),
+    splits_into: None
+};
+
+pub const SHARD: Archetype = Archetype {
+    hit_points: 1,
+    size: 0.5,
+    velocity: 0.09,
+    color: 0xFF80FF,
+    points: 50,
+    shoots_every: None,
+    splits_into: None
+};
+
+pub const SPLITTER: Archetype = Archetype {
+    hit_points: 2,
+    size: 1.2,
+    velocity: 0.06,
+    color: 0xFF00FF,
+    points: 200,
+    shoots_every: None,
+    splits_into: Some((&SHARD, 3))
+};
+
+//novelty peaks start at 50 (see audio_analysis::peak_detection), stronger onsets bring tougher enemies
+pub fn from_onset_strength(strength: f64) -> &'static Archetype {
+    if strength > 200.0 {
+        return &ARMORED;
+    }
+
+    if strength > 160.0 {
+        return &GUNNER;
+    }
+
+    if strength > 120.0 {
+        return &SPLITTER;
+    }
+
+    if strength > 80.0 {
+        return &DART;
+    }
+
+    return &DRONE;
+}
\ No newline at end of file
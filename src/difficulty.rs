@@ -0,0 +1,101 @@
+//difficulty presets are plain data like the weapons and enemy archetypes, they scale how hard the audio drives the game
+
+use recs::{Ecs, EntityId, component_filter};
+use crate::*;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    //multiplies the velocity of every enemy
+    pub enemy_speed: f32,
+    //multiplies the number of enemies in a formation
+    pub formation_size: f32,
+    //how far the normalised novelty has to rise before it counts as a peak, lower thresholds spawn on more of the song
+    pub peak_threshold: f64,
+    pub lives: i32
+}
+
+pub const EASY: Preset = Preset {
+    name: "easy",
+    enemy_speed: 0.75,
+    formation_size: 0.6,
+    peak_threshold: 65.0,
+    lives: 5
+};
+
+pub const NORMAL: Preset = Preset {
+    name: "normal",
+    enemy_speed: 1.0,
+    formation_size: 1.0,
+    peak_threshold: 50.0,
+    lives: 3
+};
+
+pub const HARD: Preset = Preset {
+    name: "hard",
+    enemy_speed: 1.3,
+    formation_size: 1.4,
+    peak_threshold: 40.0,
+    lives: 2
+};
+
+pub const EXPERT: Preset = Preset {
+    name: "expert",
+    enemy_speed: 1.6,
+    formation_size: 1.8,
+    peak_threshold: 30.0,
+    lives: 1
+};
+
+pub const PRESETS: [&'static Preset; 4] = [&EASY, &NORMAL, &HARD, &EXPERT];
+
+pub fn from_name(name: &str) -> Option<&'static Preset> {
+    return PRESETS.iter().find(|preset| preset.name == name.to_lowercase()).map(|preset| *preset);
+}
+
+//`--difficulty <easy|normal|hard|expert>` overrides the configured difficulty, which is kept when it is missing or unknown
+pub fn from_args(configured: &'static Preset) -> &'static Preset {
+    let name = match util::argument("--difficulty") {
+        Some(name) => name,
+        None => return configured
+    };
+
+    match from_name(&name) {
+        Some(preset) => return preset,
+        None => {
+            println!("[difficulty]: unknown difficulty, expected one of easy, normal, hard or expert. Playing on {}.", configured.name);
+            return configured;
+        }
+    }
+}
+
+pub fn current(store: &Ecs) -> &'static Preset {
+    let mut entities: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Difficulty), &mut entities);
+    if entities.is_empty() {
+        return &NORMAL;
+    }
+
+    return store.get::<Difficulty>(entities[0]).unwrap().preset;
+}
+
+//switching to an easier preset halfway through a game files its score under the easier one
+pub fn easier(a: &'static Preset, b: &'static Preset) -> &'static Preset {
+    let index = |preset: &'static Preset| PRESETS.iter().position(|candidate| *candidate == preset).unwrap_or(0);
+    if index(b) < index(a) {
+        return b;
+    }
+
+    return a;
+}
+
+//the preset the game in the store is filed under in the high scores
+pub fn recorded(store: &Ecs) -> &'static Preset {
+    let mut entities: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Difficulty), &mut entities);
+    if entities.is_empty() {
+        return &NORMAL;
+    }
+
+    return store.get::<Difficulty>(entities[0]).unwrap().recorded;
+}
\ No newline at end of file
@@ -1,14 +1,14 @@
 use three::{Geometry, material, Object, custom::*, custom::state::*};
-use recs::Ecs;
+use recs::{Ecs, EntityId};
 use cgmath::{Quaternion, Rad, prelude::*};
 use crate::*; 
 
-fn create_health(window: &mut three::Window, font: &three::Font) -> super::Health {
-    let mut health_ui = window.factory.ui_text(&font, "lives: 3"); 
+fn create_health(window: &mut three::Window, font: &three::Font, lives: i32) -> super::Health {
+    let mut health_ui = window.factory.ui_text(&font, format!("lives: {}", lives)); 
     health_ui.set_font_size(92.0);
     
     window.scene.add(&health_ui); 
-    return Health{total: 3, ui: health_ui}
+    return Health{total: lives, invulnerable_until: 0.0, ui: health_ui}
 }
 
 fn create_score(window: &mut three::Window, font: &three::Font) -> super::Score {
@@ -21,11 +21,62 @@ fn create_score(window: &mut three::Window, font: &three::Font) -> super::Score
     return Score{total: 0, ui: score_ui}
 }
 
-pub fn create_bullet(window: &mut three::Window, store: &mut Ecs, position: Position) {
+fn create_combo(window: &mut three::Window, font: &three::Font) -> super::Combo {
+    let mut combo_ui = window.factory.ui_text(&font, ""); 
+    combo_ui.set_font_size(64.0);
+    combo_ui.set_pos([window.size().x, 92.0]);
+    combo_ui.set_layout(three::Layout::SingleLine(three::Align::Right));
+    
+    window.scene.add(&combo_ui); 
+    return Combo{count: 0, longest: 0, ui: combo_ui}
+}
+
+fn create_accuracy(window: &mut three::Window, font: &three::Font) -> super::Accuracy {
+    let mut accuracy_ui = window.factory.ui_text(&font, ""); 
+    accuracy_ui.set_font_size(92.0);
+    accuracy_ui.set_pos([window.size().x / 2.0, 0.0]);
+    accuracy_ui.set_layout(three::Layout::SingleLine(three::Align::Center));
+    
+    window.scene.add(&accuracy_ui); 
+    return Accuracy{last: None, ui: accuracy_ui}
+}
+
+fn create_powerups(window: &mut three::Window, font: &three::Font) -> super::PowerUps {
+    let mut powerups_ui = window.factory.ui_text(&font, ""); 
+    powerups_ui.set_font_size(48.0);
+    powerups_ui.set_pos([0.0, 92.0]);
+    
+    window.scene.add(&powerups_ui); 
+    return PowerUps{
+        rapid_fire_until: 0.0,
+        spread_shot_until: 0.0,
+        slow_motion_until: 0.0,
+        shield: false,
+        ui: powerups_ui
+    }
+}
+
+fn create_weapon(window: &mut three::Window, font: &three::Font) -> super::Weapon {
+    let mut weapon_ui = window.factory.ui_text(&font, ""); 
+    weapon_ui.set_font_size(48.0);
+    weapon_ui.set_pos([0.0, window.size().y - 64.0]);
+    
+    window.scene.add(&weapon_ui); 
+    return Weapon{
+        stats: &weapon::BLASTER,
+        auto_fire: false,
+        last_shot: 0.0,
+        charge_started: None,
+        ui: weapon_ui
+    }
+}
+
+pub fn create_bullet(window: &mut three::Window, store: &mut Ecs, position: Position, projectile: Projectile, speed: f32, size: [f32; 3]) -> EntityId {
     let bullet = store.create_entity();
     let _ = store.set(bullet, Position{ x: position.x, y: position.y, z: position.z});
+    let _ = store.set(bullet, projectile);
 
-    let geometry = three::Geometry::cuboid(0.1, 0.1, 0.5); 
+    let geometry = three::Geometry::cuboid(size[0], size[1], size[2]); 
     let material = three::material::Basic {
         color: 0xFFFFFF,
         .. Default::default()
@@ -36,16 +87,65 @@ pub fn create_bullet(window: &mut three::Window, store: &mut Ecs, position: Posi
     let mesh = window.factory.mesh(geometry, material); 
     window.scene.add(&mesh);
 
-    let _ = store.set(bullet, GameObject{mesh: mesh, object_type: GameObjectType::Bullet, vertices: vertices, velocity: 0.25});
+    let _ = store.set(bullet, GameObject{mesh: mesh, object_type: GameObjectType::Bullet, vertices: vertices, velocity: speed});
+    return bullet;
+}
+
+pub fn create_hostile_bullet(window: &mut three::Window, store: &mut Ecs, position: Position, direction: [f32; 3]) {
+    let bullet = store.create_entity();
+    let _ = store.set(bullet, position);
+    let _ = store.set(bullet, Projectile{direction: direction, damage: 1, piercing: false, expires: None, hits: Vec::new()});
+
+    let geometry = three::Geometry::uv_sphere(0.2, 8, 8); 
+    let material = three::material::Basic {
+        color: 0xFF1493,
+        .. Default::default()
+    };
+
+    let vertices = geometry.base.vertices.clone();
+
+    let mesh = window.factory.mesh(geometry, material); 
+    mesh.set_position([position.x, position.y, position.z]);
+    window.scene.add(&mesh);
+    let _ = store.set(bullet, GameObject{mesh: mesh, object_type: GameObjectType::HostileBullet, vertices: vertices, velocity: 0.15});
+}
+
+pub fn create_pickup(window: &mut three::Window, store: &mut Ecs, position: Position, kind: PickupKind) {
+    let pickup = store.create_entity();
+    let _ = store.set(pickup, position);
+    let _ = store.set(pickup, Pickup{kind: kind});
+
+    let color = match kind {
+        PickupKind::ExtraLife => 0x00FF00,
+        PickupKind::RapidFire => 0xFF8C00,
+        PickupKind::SpreadShot => 0x00FFFF,
+        PickupKind::Shield => 0x4169E1,
+        PickupKind::SlowMotion => 0xEE82EE
+    };
+
+    let geometry = three::Geometry::uv_sphere(0.4, 12, 12); 
+    let material = three::material::Basic {
+        color: color,
+        .. Default::default()
+    };
+
+    let vertices = geometry.base.vertices.clone();
+
+    let mesh = window.factory.mesh(geometry, material); 
+    mesh.set_position([position.x, position.y, position.z]);
+    window.scene.add(&mesh);
+    let _ = store.set(pickup, GameObject{mesh: mesh, object_type: GameObjectType::Pickup, vertices: vertices, velocity: 0.06});
 }
 
-pub fn create_enemy(window: &mut three::Window, store: &mut Ecs, position: Position) {
+pub fn create_enemy(window: &mut three::Window, store: &mut Ecs, position: Position, movement: Movement, archetype: &'static archetype::Archetype) {
     let cube = store.create_entity();
     let _ = store.set(cube, position);
+    let _ = store.set(cube, movement);
+    let _ = store.set(cube, Enemy{archetype: archetype, hit_points: archetype.hit_points});
 
-    let geometry = three::Geometry::cuboid(1.0, 1.0, 1.0); 
+    let geometry = three::Geometry::cuboid(archetype.size, archetype.size, archetype.size); 
     let material = three::material::Basic {
-        color: 0xFF0000,
+        color: archetype.color,
         .. Default::default()
     };
 
@@ -54,18 +154,90 @@ pub fn create_enemy(window: &mut three::Window, store: &mut Ecs, position: Posit
     let mesh = window.factory.mesh(geometry, material); 
     mesh.set_position([position.x, position.y, position.z]);
     window.scene.add(&mesh);
-    let _ = store.set(cube, GameObject{mesh: mesh, object_type: GameObjectType::Enemy, vertices: vertices, velocity: 0.07});
+    system::statistics::enemy_spawned(store);
+
+    let velocity = archetype.velocity * difficulty::current(store).enemy_speed;
+    let _ = store.set(cube, GameObject{mesh: mesh, object_type: GameObjectType::Enemy, vertices: vertices, velocity: velocity});
+}
+
+fn create_boss_part(window: &mut three::Window, store: &mut Ecs, boss: EntityId, anchor: Position, radius: f32, angle: f32, size: f32, color: u32) {
+    let part = store.create_entity();
+    let offset = util::polar_to_cartesian(radius, angle);
+    let position = Position{ x: anchor.x + offset[0], y: anchor.y + offset[1], z: anchor.z };
+    let _ = store.set(part, position);
+    let _ = store.set(part, BossPart{boss: boss, radius: radius, angle: angle});
+
+    let geometry = three::Geometry::cuboid(size, size, size); 
+    let material = three::material::Basic {
+        color: color,
+        .. Default::default()
+    };
+
+    let vertices = geometry.base.vertices.clone();
+
+    let mesh = window.factory.mesh(geometry, material); 
+    mesh.set_position([position.x, position.y, position.z]);
+    window.scene.add(&mesh);
+    let _ = store.set(part, GameObject{mesh: mesh, object_type: GameObjectType::BossPart, vertices: vertices, velocity: 0.05});
+}
+
+pub fn create_boss(window: &mut three::Window, store: &mut Ecs) {
+    let boss = store.create_entity();
+    let anchor = Position{ x: 0.0, y: 0.0, z: -30.0 };
+
+    let font = window.factory.load_font_karla();
+    let mut health_ui = window.factory.ui_text(&font, ""); 
+    health_ui.set_font_size(64.0);
+    health_ui.set_pos([window.size().x / 2.0, window.size().y - 80.0]);
+    health_ui.set_layout(three::Layout::SingleLine(three::Align::Center));
+    window.scene.add(&health_ui); 
+
+    let _ = store.set(boss, Boss{
+        health: 60,
+        max_health: 60,
+        anchor: anchor,
+        rotation: 0.0,
+        lunge: 0.0,
+        beats: 0,
+        retreating: false,
+        ui: health_ui
+    });
+
+    //a large core with four arms around it
+    create_boss_part(window, store, boss, anchor, 0.0, 0.0, 2.5, 0xFF4500);
+    for index in 0..4 {
+        create_boss_part(window, store, boss, anchor, 3.0, 90.0 * (index as f32), 1.2, 0xFFA500);
+    }
 }
 
-pub fn create_player(mut window: &mut three::Window, store: &mut Ecs) {
+pub fn create_player(mut window: &mut three::Window, store: &mut Ecs, difficulty: &difficulty::Preset) {
     let player = store.create_entity();
     let _ = store.set(player, Position{ x: 0.0, y: 0.0, z: 0.0});
+    let _ = store.set(player, Steering::Free);
 
     let font = window.factory.load_font_karla();
     let score = create_score(&mut window, &font);
     let _ = store.set(player, score);
-    let health = create_health(&mut window, &font);
+    let combo = create_combo(&mut window, &font);
+    let _ = store.set(player, combo);
+    let health = create_health(&mut window, &font, difficulty.lives);
     let _ = store.set(player, health);
+    let accuracy = create_accuracy(&mut window, &font);
+    let _ = store.set(player, accuracy);
+    let powerups = create_powerups(&mut window, &font);
+    let _ = store.set(player, powerups);
+    let weapon = create_weapon(&mut window, &font);
+    let _ = store.set(player, weapon);
+    let _ = store.set(player, Statistics{
+        judgements: [0; 4],
+        projectiles_hit: 0,
+        projectiles_missed: 0,
+        damage_taken: 0,
+        enemies_spawned: 0,
+        enemies_destroyed: 0,
+        timeline: Vec::new(),
+        next_sample: 0.0
+    });
 
     let basic_pipeline = window.factory.basic_pipeline(
             "./src/shaders",
@@ -94,8 +266,24 @@ pub fn create_player(mut window: &mut three::Window, store: &mut Ecs) {
     let _ = store.set(player, GameObject{mesh: mesh, object_type: GameObjectType::Player, vertices: vertices, velocity: 0.07});
 }
 
-pub fn create_tunnel(window: &mut three::Window) {
-    let (_, meshes) = window.factory.load_obj("./src/models/tunnel/hollow_cylinder.obj");
+//three doesn't expose the vertices of loaded meshes, so the obj is read once more to find how wide the tunnel is inside
+fn tunnel_inner_radius(path: &str) -> f32 {
+    let source = std::fs::read_to_string(path).expect("unable to read tunnel model");
+
+    //the cylinder is modelled around the y-axis, its rotated onto the z-axis in create_tunnel
+    return source.lines()
+        .filter(|line| line.starts_with("v "))
+        .map(|line| {
+            let coordinates: Vec<f32> = line.split_whitespace().skip(1).map(|value| value.parse().unwrap_or(0.0)).collect();
+            return (coordinates[0] * coordinates[0] + coordinates[2] * coordinates[2]).sqrt();
+        })
+        .fold(std::f32::MAX, |min, radius| min.min(radius));
+}
+
+pub fn create_tunnel(window: &mut three::Window, store: &mut Ecs) {
+    let path = "./src/models/tunnel/hollow_cylinder.obj";
+    let scale: f32 = 8.0;
+    let (_, meshes) = window.factory.load_obj(path);
     let mesh = &meshes[0];
 
     let material = material::Basic {
@@ -106,7 +294,57 @@ pub fn create_tunnel(window: &mut three::Window) {
     mesh.set_material(material); 
     let angle: f32 = 90.0;
     mesh.set_orientation(Quaternion::from_angle_x(Rad(angle.to_radians()))); 
-    mesh.set_scale(8.0);
+    mesh.set_scale(scale);
     mesh.set_position([0.0, 0.0, -15.0]);
     window.scene.add(&mesh); 
+
+    let tunnel = store.create_entity();
+    let _ = store.set(tunnel, Tunnel{radius: tunnel_inner_radius(path) * scale});
+}
+
+pub fn create_damage_feedback(window: &mut three::Window, volume: f32) -> DamageFeedback {
+    let mut source = window.factory.audio_source();
+    source.set_volume(volume);
+    window.scene.add(&source);
+    let hit_sound = window.factory.load_audio("./src/sounds/hit.wav");
+
+    return DamageFeedback{shake_until: 0.0, source: source, hit_sound: hit_sound};
+}
+
+pub fn create_menu(window: &mut three::Window) -> three::Text {
+    let font = window.factory.load_font_karla();
+    let mut menu = window.factory.ui_text(&font, "");
+    menu.set_font_size(64.0);
+    menu.set_pos([window.size().x / 2.0, window.size().y / 3.0]);
+    menu.set_layout(three::Layout::Wrap(three::Align::Center));
+    return menu;
+}
+
+//the score over the game as a bar graph below the results text, long games are squeezed into a fixed number of bars
+pub fn create_score_graph(window: &mut three::Window, timeline: &Vec<i32>) -> Vec<three::Mesh> {
+    let max_bars = 40;
+    let (width, height, bottom) = (16.0, 5.0, -6.0);
+
+    let bars = timeline.len().min(max_bars);
+    if bars == 0 {
+        return Vec::new();
+    }
+
+    let highest = timeline.iter().cloned().max().unwrap_or(0).max(1) as f32;
+    let bar_width = width / (bars as f32);
+    return (0..bars).map(|bar| {
+        let score = timeline[bar * timeline.len() / bars] as f32;
+        let bar_height = (score / highest * height).max(0.05);
+
+        let geometry = three::Geometry::cuboid(bar_width * 0.8, bar_height, 0.1);
+        let material = three::material::Basic {
+            color: 0x00FF7F,
+            .. Default::default()
+        };
+
+        let mesh = window.factory.mesh(geometry, material);
+        mesh.set_position([-width / 2.0 + bar_width * ((bar as f32) + 0.5), bottom + bar_height / 2.0, 0.0]);
+        window.scene.add(&mesh);
+        return mesh;
+    }).collect();
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+use rand::Rng;
+use crate::*;
+
+//the shapes enemies can spawn in, picked by select based on the audio that triggered the spawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Formation {
+    Ring,
+    Spiral,
+    Line,
+    Grid,
+    VShape,
+    Wall,
+    Scatter
+}
+
+//the random part of a formation, rolled by the enemy scheduler. radius and rotation are in tunnel units and degrees
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FormationParameters {
+    pub count: i32,
+    pub radius: f32,
+    pub z: f32,
+    pub rotation: f32
+}
+
+//strong onsets get dense formations, a bright sound (high spectral centroid) gets the sharper shapes
+pub fn select(onset_strength: f64, spectral_centroid: f64) -> Formation {
+    let bright = spectral_centroid > 0.25;
+
+    if onset_strength > 150.0 {
+        return if bright { Formation::VShape } else { Formation::Wall };
+    }
+
+    if onset_strength > 90.0 {
+        return if bright { Formation::Spiral } else { Formation::Grid };
+    }
+
+    if onset_strength > 65.0 {
+        return if bright { Formation::Line } else { Formation::Ring };
+    }
+
+    return if bright { Formation::Scatter } else { Formation::Ring };
+}
+
+pub fn build(formation: Formation, parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    match formation {
+        Formation::Ring => ring(parameters),
+        Formation::Spiral => spiral(parameters),
+        Formation::Line => line(parameters),
+        Formation::Grid => grid(parameters),
+        Formation::VShape => v_shape(parameters),
+        Formation::Wall => wall(parameters),
+        Formation::Scatter => scatter(parameters)
+    }
+}
+
+fn rotate(x: f32, y: f32, angle: f32) -> [f32; 2] {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    return [x * cos - y * sin, x * sin + y * cos];
+}
+
+fn ring_movement(radius: f32) -> Movement {
+    let mut random = rand::thread_rng();
+    let direction = if random.gen() { 1.0 } else { -1.0 };
+
+    match random.gen_range(0, 5) {
+        0 => Movement::Spiral{ angular_velocity: direction * random.gen_range(0.5, 1.5), radius: radius, pulse: 0.0 },
+        1 => Movement::Spiral{ angular_velocity: direction * random.gen_range(0.2, 0.8), radius: radius, pulse: random.gen_range(0.5, 1.5) },
+        2 => Movement::Weave{ amplitude: random.gen_range(0.5, 1.5), frequency: random.gen_range(0.3, 0.8) },
+        3 => Movement::Hop{ distance: random.gen_range(1.0, 2.0) },
+        _ => Movement::Straight
+    }
+}
+
+fn ring(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let d_angle = 360.0 / (parameters.count as f32);
+    //the whole ring moves the same way so it rotates and breathes as one
+    let movement = ring_movement(parameters.radius);
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for index in 0..parameters.count {
+        let cartesian_coordinates = util::polar_to_cartesian(parameters.radius, parameters.rotation + d_angle * ((index as f32) + 1.0));
+
+        pending_enemies.push((Position{
+                x: cartesian_coordinates[0],
+                y: cartesian_coordinates[1],
+                z: parameters.z
+            }, movement));
+    }
+
+    return pending_enemies;
+}
+
+//one and a half turns winding outward and backward, every enemy keeps orbiting at its own radius
+fn spiral(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let d_angle = 540.0 / (parameters.count as f32);
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for index in 0..parameters.count {
+        let progress = (index as f32) / (parameters.count as f32);
+        let radius = 1.0 + (parameters.radius - 1.0) * progress;
+        let cartesian_coordinates = util::polar_to_cartesian(radius, parameters.rotation + d_angle * (index as f32));
+
+        pending_enemies.push((Position{
+                x: cartesian_coordinates[0],
+                y: cartesian_coordinates[1],
+                z: parameters.z - (index as f32) * 0.8
+            }, Movement::Spiral{ angular_velocity: 1.0, radius: radius, pulse: 0.0 }));
+    }
+
+    return pending_enemies;
+}
+
+fn line(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let spacing = (parameters.radius * 2.0) / ((parameters.count - 1).max(1) as f32);
+    let movement = Movement::Weave{ amplitude: 1.0, frequency: 0.5 };
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for index in 0..parameters.count {
+        let cartesian_coordinates = rotate(-parameters.radius + spacing * (index as f32), 0.0, parameters.rotation);
+
+        pending_enemies.push((Position{
+                x: cartesian_coordinates[0],
+                y: cartesian_coordinates[1],
+                z: parameters.z
+            }, movement));
+    }
+
+    return pending_enemies;
+}
+
+//rows are filled one after another, the last row can be partly empty
+fn grid(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let side = (parameters.count.max(1) as f32).sqrt().ceil() as i32;
+    let spacing = if side > 1 { (parameters.radius * 2.0) / ((side - 1) as f32) } else { 0.0 };
+    let offset = if side > 1 { parameters.radius } else { 0.0 };
+    let movement = Movement::Hop{ distance: 1.5 };
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for index in 0..parameters.count {
+        let cartesian_coordinates = rotate(
+            -offset + spacing * ((index % side) as f32),
+            -offset + spacing * ((index / side) as f32),
+            parameters.rotation
+        );
+
+        pending_enemies.push((Position{
+                x: cartesian_coordinates[0],
+                y: cartesian_coordinates[1],
+                z: parameters.z
+            }, movement));
+    }
+
+    return pending_enemies;
+}
+
+//the tip leads and both arms trail backward and outward
+//with an even count one arm is a step longer than the other
+fn v_shape(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    if parameters.count <= 0 {
+        return Vec::new();
+    }
+
+    let arm_length = (parameters.count / 2).max(1);
+    let spacing = parameters.radius / (arm_length as f32);
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    pending_enemies.push((Position{ x: 0.0, y: 0.0, z: parameters.z }, Movement::Straight));
+
+    for step in 1..(arm_length + 1) {
+        for side in [-1.0f32, 1.0].iter() {
+            if pending_enemies.len() as i32 >= parameters.count {
+                break;
+            }
+
+            let cartesian_coordinates = rotate(side * spacing * (step as f32), spacing * (step as f32), parameters.rotation);
+
+            pending_enemies.push((Position{
+                    x: cartesian_coordinates[0],
+                    y: cartesian_coordinates[1],
+                    z: parameters.z - (step as f32) * 1.0
+                }, Movement::Straight));
+        }
+    }
+
+    return pending_enemies;
+}
+
+//a square wall covering the tunnel with a 2x2 hole somewhere in it
+//it is as large as the count allows without being packed tighter than 1.5 apart, too few enemies for a hole make a line instead
+fn wall(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let max_side = ((parameters.radius * 2.0) / 1.5) as i32 + 1;
+    let side = ((parameters.count + 4) as f32).sqrt().floor().min(max_side as f32) as i32;
+    if side < 3 {
+        return line(parameters);
+    }
+
+    let mut random = rand::thread_rng();
+    let spacing = (parameters.radius * 2.0) / ((side - 1) as f32);
+    let gap_row = random.gen_range(0, side - 1);
+    let gap_column = random.gen_range(0, side - 1);
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for row in 0..side {
+        for column in 0..side {
+            if (row == gap_row || row == gap_row + 1) && (column == gap_column || column == gap_column + 1) {
+                continue;
+            }
+
+            pending_enemies.push((Position{
+                    x: -parameters.radius + spacing * (column as f32),
+                    y: -parameters.radius + spacing * (row as f32),
+                    z: parameters.z
+                }, Movement::Straight));
+        }
+    }
+
+    return pending_enemies;
+}
+
+fn scatter(parameters: &FormationParameters) -> Vec<(Position, Movement)> {
+    let mut random = rand::thread_rng();
+
+    let mut pending_enemies: Vec<(Position, Movement)> = Vec::new();
+    for _ in 0..parameters.count {
+        let radius = parameters.radius * random.gen_range(0.0f32, 1.0).sqrt();
+        let cartesian_coordinates = util::polar_to_cartesian(radius, random.gen_range(0.0, 360.0));
+
+        pending_enemies.push((Position{
+                x: cartesian_coordinates[0],
+                y: cartesian_coordinates[1],
+                z: parameters.z + random.gen_range(-2.0, 2.0)
+            }, Movement::Straight));
+    }
+
+    return pending_enemies;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: [Formation; 6] = [Formation::Ring, Formation::Spiral, Formation::Line, Formation::Grid, Formation::VShape, Formation::Scatter];
+
+    fn parameters(count: i32) -> FormationParameters {
+        return FormationParameters{ count: count, radius: 5.0, z: -30.0, rotation: 0.0 };
+    }
+
+    #[test]
+    fn shapes_spawn_exactly_count_enemies() {
+        for shape in SHAPES.iter() {
+            for count in 0..50 {
+                assert_eq!(build(*shape, &parameters(count)).len() as i32, count, "{:?} with {}", shape, count);
+            }
+        }
+    }
+
+    #[test]
+    fn wall_never_spawns_more_than_count() {
+        for count in 0..60 {
+            let enemies = build(Formation::Wall, &parameters(count)).len() as i32;
+            assert!(enemies <= count, "wall of {} for {}", enemies, count);
+            assert!(count == 0 || enemies > 0);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,149 @@
+//gamepads feed the same actions as the keyboard, gilrs keeps track of them being plugged in and out
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+use crate::action::{Action, Controls};
+
+//stick movement below this is treated as the stick resting in the middle
+const DEADZONE: f32 = 0.2;
+
+//the sticks and the d-pad move, the right trigger fires
+const BUTTONS: [(Button, Action); 15] = [
+    (Button::DPadUp, Action::MoveUp),
+    (Button::DPadDown, Action::MoveDown),
+    (Button::DPadLeft, Action::MoveLeft),
+    (Button::DPadRight, Action::MoveRight),
+    (Button::RightTrigger2, Action::Fire),
+    (Button::North, Action::ToggleAutoFire),
+    (Button::West, Action::ToggleOrbit),
+    (Button::LeftTrigger, Action::Weapon1),
+    (Button::RightTrigger, Action::Weapon2),
+    (Button::LeftTrigger2, Action::Weapon3),
+    (Button::LeftThumb, Action::Weapon4),
+    (Button::Start, Action::Pause),
+    (Button::East, Action::Pause),
+    (Button::South, Action::Confirm),
+    (Button::RightThumb, Action::Confirm)
+];
+
+//none when the platform has no gamepad support, the game is then played with the keyboard only
+pub fn connect() -> Option<Gilrs> {
+    match Gilrs::new() {
+        Ok(gilrs) => {
+            for (_, gamepad) in gilrs.gamepads() {
+                println!("[gamepad]: found {}", gamepad.name());
+            }
+            return Some(gilrs);
+        },
+        Err(err) => {
+            println!("[gamepad]: gamepads are not supported. {:?}", err);
+            return None;
+        }
+    }
+}
+
+fn action(button: Button) -> Option<Action> {
+    return BUTTONS.iter().find(|(candidate, _)| *candidate == button).map(|(_, action)| *action);
+}
+
+fn add(actions: &mut Vec<Action>, action: Action) {
+    if !actions.contains(&action) {
+        actions.push(action);
+    }
+}
+
+//the stick replaces the digital movement so a half tilt moves the player at half speed
+fn apply_stick(controls: &mut Controls, stick: [f32; 2]) {
+    if (stick[0] * stick[0] + stick[1] * stick[1]).sqrt() > DEADZONE {
+        controls.movement = stick;
+    }
+}
+
+//the d-pad moves like the keyboard does, on the axes nothing else is moving on yet
+//held only has the gamepad's own actions, keys that cancel each other out on the keyboard must not count as the d-pad
+fn apply_dpad(controls: &mut Controls, held: &Vec<Action>) {
+    for (axis, positive, negative) in [(0usize, Action::MoveRight, Action::MoveLeft), (1usize, Action::MoveUp, Action::MoveDown)].iter() {
+        if controls.movement[*axis] == 0.0 {
+            let mut value = 0.0;
+            if held.contains(positive) {
+                value = value + 1.0;
+            }
+            if held.contains(negative) {
+                value = value - 1.0;
+            }
+            controls.movement[*axis] = value;
+        }
+    }
+}
+
+//every connected gamepad is read, so one can be swapped for another at any time
+pub fn read(gilrs: &mut Option<Gilrs>, controls: &mut Controls) {
+    let gilrs = match gilrs {
+        Some(gilrs) => gilrs,
+        None => return
+    };
+
+    while let Some(event) = gilrs.next_event() {
+        match event.event {
+            EventType::ButtonPressed(button, _) => {
+                if let Some(action) = action(button) {
+                    add(&mut controls.pressed, action);
+                }
+            },
+            EventType::Connected => println!("[gamepad]: connected {}", gilrs.gamepad(event.id).name()),
+            EventType::Disconnected => println!("[gamepad]: disconnected {}", gilrs.gamepad(event.id).name()),
+            _ => ()
+        }
+    }
+
+    let mut held: Vec<Action> = Vec::new();
+    for (_, gamepad) in gilrs.gamepads() {
+        for (button, action) in BUTTONS.iter() {
+            if gamepad.is_pressed(*button) {
+                add(&mut held, *action);
+                add(&mut controls.held, *action);
+            }
+        }
+
+        apply_stick(controls, [gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY)]);
+    }
+
+    apply_dpad(controls, &held);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controls(held: Vec<Action>, movement: [f32; 2]) -> Controls {
+        return Controls{ pressed: Vec::new(), held: held, movement: movement };
+    }
+
+    #[test]
+    fn stick_inside_the_deadzone_is_ignored() {
+        let mut keyboard = controls(vec![Action::MoveRight], [1.0, 0.0]);
+        apply_stick(&mut keyboard, [0.1, -0.1]);
+        assert_eq!(keyboard.movement, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn stick_replaces_digital_movement() {
+        let mut keyboard = controls(vec![Action::MoveRight], [1.0, 0.0]);
+        apply_stick(&mut keyboard, [-0.5, 0.25]);
+        assert_eq!(keyboard.movement, [-0.5, 0.25]);
+    }
+
+    #[test]
+    fn dpad_only_moves_on_idle_axes() {
+        let mut gamepad = controls(vec![Action::MoveLeft, Action::MoveUp], [0.5, 0.0]);
+        apply_dpad(&mut gamepad, &vec![Action::MoveLeft, Action::MoveUp]);
+        assert_eq!(gamepad.movement, [0.5, 1.0]);
+    }
+
+    #[test]
+    fn dpad_ignores_keys_that_cancel_out() {
+        let mut keyboard = controls(vec![Action::MoveLeft, Action::MoveRight], [0.0, 0.0]);
+        apply_dpad(&mut keyboard, &Vec::new());
+        assert_eq!(keyboard.movement, [0.0, 0.0]);
+    }
+}
\ No newline at end of file
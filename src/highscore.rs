@@ -0,0 +1,37 @@
+//high scores are kept in a plain text file, one `<difficulty> <score>` pair per line
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use crate::*;
+
+const PATH: &str = "./highscores.txt";
+
+pub fn record(score: i32, difficulty: &difficulty::Preset) {
+    let file = OpenOptions::new().create(true).append(true).open(PATH);
+    match file {
+        Ok(mut file) => {
+            if let Err(err) = writeln!(file, "{} {}", difficulty.name, score) {
+                println!("[highscore]: unable to record score. {:?}", err);
+            }
+        },
+        Err(err) => println!("[highscore]: unable to open {}. {:?}", PATH, err)
+    }
+}
+
+//every recorded score as difficulty and score, best first
+pub fn load() -> Vec<(String, i32)> {
+    let contents = match fs::read_to_string(PATH) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new()
+    };
+
+    let mut scores: Vec<(String, i32)> = contents.lines().filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        let difficulty = parts.next()?.to_string();
+        let score = parts.next()?.parse::<i32>().ok()?;
+        return Some((difficulty, score));
+    }).collect();
+
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+    return scores;
+}
\ No newline at end of file
@@ -1,20 +1,32 @@
 use std::sync::mpsc::*;
 use three; 
 use three::Object;
-use recs::{Ecs};
+use recs::{Ecs, EntityId};
 use mint::Point3;
 use clokwerk::{Scheduler, TimeUnits};
 use std::collections::VecDeque;
 
+mod action;
+mod archetype;
+mod difficulty;
 mod factory;
+mod formation;
+mod gamepad;
+mod highscore;
+mod settings;
 mod util; 
+mod weapon;
 mod system;
+mod track;
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum GameObjectType {
     Player,
     Enemy,
-    Bullet
+    Bullet,
+    BossPart,
+    Pickup,
+    HostileBullet
 }
 
 #[derive(Clone, PartialEq, Debug, Copy)]
@@ -24,6 +36,20 @@ pub struct Position {
     z: f32
 }
 
+//how far an entity moved during the last position update, used for swept collision
+#[derive(Clone, PartialEq, Debug, Copy)]
+pub struct Displacement {
+    x: f32,
+    y: f32,
+    z: f32
+}
+
+//inner radius of the tunnel in world units, nothing the player controls should leave it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tunnel {
+    radius: f32
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct GameObject {
     mesh: three::Mesh,
@@ -32,6 +58,172 @@ pub struct GameObject {
     velocity: f32
 }
 
+//how an enemy moves on top of travelling down the tunnel, angles are in degrees
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Movement {
+    Straight,
+    //orbits the tunnel axis, the radius swells by pulse on every beat
+    Spiral { angular_velocity: f32, radius: f32, pulse: f32 },
+    //sways from side to side while moving forward
+    Weave { amplitude: f32, frequency: f32 },
+    //steers a fraction of the distance toward the player every frame
+    Homing { turn_rate: f32 },
+    //barely moves between beats and jumps forward on a beat
+    Hop { distance: f32 }
+}
+
+//the direction a projectile travels in is multiplied by the velocity of its GameObject
+//piercing projectiles aren't used up on a hit, they remember what they hit so they only hit it once
+#[derive(Clone, PartialEq, Debug)]
+pub struct Projectile {
+    direction: [f32; 3],
+    damage: i32,
+    piercing: bool,
+    expires: Option<f32>,
+    hits: Vec<EntityId>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Weapon {
+    stats: &'static weapon::WeaponStats,
+    auto_fire: bool,
+    last_shot: f32,
+    charge_started: Option<f32>,
+    ui: three::Text
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PickupKind {
+    ExtraLife,
+    RapidFire,
+    SpreadShot,
+    Shield,
+    SlowMotion
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pickup {
+    kind: PickupKind
+}
+
+//timed power-ups hold the time (in seconds, like Rhythm.now) they run out
+#[derive(Clone, PartialEq, Debug)]
+pub struct PowerUps {
+    rapid_fire_until: f32,
+    spread_shot_until: f32,
+    slow_motion_until: f32,
+    shield: bool,
+    ui: three::Text
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Enemy {
+    archetype: &'static archetype::Archetype,
+    hit_points: i32
+}
+
+//a boss is a group of parts rotating around an anchor, the Boss itself has no mesh but keeps the shared health
+#[derive(Clone, PartialEq, Debug)]
+pub struct Boss {
+    health: i32,
+    max_health: i32,
+    anchor: Position,
+    rotation: f32,
+    //progress of the arms swinging out to the player and back, 0.0 while not lunging and counting up to 1.0
+    lunge: f32,
+    beats: i32,
+    retreating: bool,
+    ui: three::Text
+}
+
+//placement of a part relative to the anchor of its boss, angle is in degrees
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BossPart {
+    boss: EntityId,
+    radius: f32,
+    angle: f32
+}
+
+//shakes the camera and plays a sound when the player gets hurt
+pub struct DamageFeedback {
+    shake_until: f32,
+    source: three::audio::Source,
+    hit_sound: three::audio::Clip
+}
+
+//what the game is currently doing, the simulation only runs while playing
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameState {
+    Title { selected: usize },
+    TrackSelect { selected: usize },
+    HighScores,
+    Results,
+    //settings can be opened from the title screen and from the pause menu, paused_since leads back to the latter
+    Settings { selected: usize, paused_since: Option<f32> },
+    //listening waits for the key that is added to or removed from the selected action
+    Bindings { selected: usize, listening: bool, paused_since: Option<f32> },
+    Playing,
+    Paused { since: f32, selected: usize }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuAction {
+    Play,
+    Resume,
+    Restart,
+    BackToTitle,
+    //changes show up right away, they are only written to disk once the settings screen is left
+    ApplySettings,
+    SaveSettings,
+    Quit
+}
+
+//choices made in the menus, they carry over into every game
+#[derive(Clone, PartialEq, Debug)]
+pub struct Session {
+    settings: settings::Settings,
+    //names of the microphones that can be picked in the settings
+    input_devices: Vec<String>,
+    track_directory: String,
+    tracks: Vec<track::Track>,
+    //index into tracks, the microphone is used when no track is picked
+    track: Option<usize>,
+    //the picked track decoded, it is only decoded once when it is picked
+    audio: Option<track::Audio>,
+    //seconds a game with the microphone lasts, it goes on until the player runs out of lives without one
+    duration: Option<f32>
+}
+
+//a track played from a file, the analysis reads the same samples that are playing
+pub struct Playback {
+    audio: track::Audio,
+    beatmap: Option<track::Beatmap>,
+    source: three::audio::Source
+}
+
+//the difficulty preset the game is played on, every system can look it up
+#[derive(Clone, PartialEq, Debug)]
+pub struct Difficulty {
+    preset: &'static difficulty::Preset,
+    //the easiest preset used during the game, its score is filed under this one
+    recorded: &'static difficulty::Preset
+}
+
+//tracks how long the music has been intense or calm, a boss shows up when an intense section lasts
+#[derive(Clone, PartialEq, Debug)]
+pub struct Encounter {
+    intense_since: Option<f32>,
+    calm_since: Option<f32>,
+    next_boss: f32
+}
+
+//Free flies anywhere inside the tunnel, Orbit sticks to the tunnel wall and moves by angle (in degrees)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Steering {
+    Free,
+    Orbit { angle: f32 }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Score {
     total: i32,
@@ -41,31 +233,238 @@ pub struct Score {
 #[derive(Clone, PartialEq, Debug)]
 pub struct Health {
     total: i32,
+    //the player can't be hurt again until this time, gives them a moment to get out of trouble
+    invulnerable_until: f32,
     ui: three::Text
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct Combo {
+    count: i32,
+    longest: i32,
+    ui: three::Text
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Accuracy {
+    last: Option<Judgement>,
+    ui: three::Text
+}
+
+//everything worth showing on the results screen, gathered over the whole game
+#[derive(Clone, PartialEq, Debug)]
+pub struct Statistics {
+    //shots per judgement in the order perfect, great, good and miss
+    judgements: [i32; 4],
+    projectiles_hit: i32,
+    projectiles_missed: i32,
+    damage_taken: i32,
+    enemies_spawned: i32,
+    enemies_destroyed: i32,
+    //the score sampled at a fixed interval
+    timeline: Vec<i32>,
+    next_sample: f32
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Judgement {
+    Perfect,
+    Great,
+    Good,
+    Miss
+}
+
+//time of the current frame and of the last detected beat in seconds since the game started, beats counts detected beats
+//interval is the estimated time between beats, it is used to predict where the next beat will land
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rhythm {
+    now: f32,
+    last_beat: f32,
+    interval: f32,
+    beats: i32
+}
+
+//events are emitted during a frame, every system can react to them and they are cleared at the end of the frame
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    Beat,
+    PlayerHit { position: Position },
+    ShotFired { judgement: Judgement },
+    //every projectile of the player ends up as either a hit or a miss, a piercing one counts once however many it passes through
+    ProjectileHit,
+    ProjectileMissed,
+    EnemyDestroyed { by: GameObjectType, archetype: &'static archetype::Archetype, judgement: Option<Judgement>, position: Position, beat_offset: f32 },
+    ShieldBroken,
+    PickupCollected { kind: PickupKind },
+    BossHit { boss: EntityId, damage: i32, judgement: Option<Judgement>, position: Position },
+    BossDefeated { position: Position }
+}
+
+//systems mark entities here instead of removing them directly so each one is removed exactly once at the end of the frame
+#[derive(Clone, PartialEq, Debug)]
+pub struct CommandBuffer {
+    despawn: Vec<EntityId>
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct AudioHistory {
     spectrum: VecDeque<Vec<f64>>,
     novelty: VecDeque<f64>,
     normalised_novelty: VecDeque<f64>,
-    last_peak: f64
+    last_peak: f64,
+    //center of mass of the latest spectrum, 0.0 is all bass and 1.0 is all treble
+    spectral_centroid: f64,
+    //running averages of loudness (rms) and novelty over a short and a long window, used to find song sections
+    short_energy: f64,
+    long_energy: f64,
+    short_novelty: f64,
+    long_novelty: f64,
+    //number of buffers analysed so far, the long averages mean nothing during the first seconds
+    analysed: i32
+}
+
+//samples per block of audio handed to the analysis, for the microphone as well as for tracks
+const ANALYSIS_BUFFER: usize = 256;
+
+//sets up everything a fresh game needs in the current scene, a picked track starts playing right away
+fn start(window: &mut three::Window, session: &Session) -> (Ecs, Option<Playback>) {
+    let mut store = Ecs::new();
+    let difficulty = store.create_entity();
+    let _ = store.set(difficulty, Difficulty{preset: session.settings.difficulty, recorded: session.settings.difficulty});
+    factory::create_tunnel(window, &mut store);
+    factory::create_player(window, &mut store, session.settings.difficulty);
+
+    let playback = match (session.track, &session.audio) {
+        (Some(index), Some(audio)) => {
+            let track = &session.tracks[index];
+            let clip = window.factory.load_audio(&track.path);
+            let mut source = window.factory.audio_source();
+            source.set_volume(session.settings.volume);
+            window.scene.add(&source);
+            source.play(&clip);
+            Some(Playback{ audio: audio.clone(), beatmap: track.beatmap, source: source })
+        },
+        (Some(index), None) => {
+            println!("[start]: unable to decode {:?}, listening to the microphone instead.", session.tracks[index].path);
+            None
+        },
+        _ => None
+    };
+
+    return (store, playback);
+}
+
+fn new_audio_history() -> AudioHistory {
+    return AudioHistory {
+        spectrum: VecDeque::new(),
+        novelty: VecDeque::new(),
+        normalised_novelty: VecDeque::new(),
+        last_peak: 0.0,
+        spectral_centroid: 0.0,
+        short_energy: 0.0,
+        long_energy: 0.0,
+        short_novelty: 0.0,
+        long_novelty: 0.0,
+        analysed: 0
+    };
+}
+
+fn input_devices(pa: &portaudio::PortAudio) -> Vec<String> {
+    let devices = match pa.devices() {
+        Ok(devices) => devices,
+        Err(err) => {
+            println!("[input_devices]: unable to list audio devices. {:?}", err);
+            return Vec::new();
+        }
+    };
+
+    return devices.filter_map(|device| device.ok())
+        .filter(|(_, info)| info.max_input_channels > 0)
+        .map(|(_, info)| info.name.to_string())
+        .collect();
+}
+
+//opens the microphone with the given name, the default input device is used when there is none or it can't be found
+fn open_microphone(pa: &portaudio::PortAudio, name: &Option<String>, mic_sender: Sender<&'static [f32]>) -> portaudio::Stream<portaudio::NonBlocking, portaudio::Input<f32>> {
+    let named_mic_index = match (name, pa.devices()) {
+        (Some(name), Ok(devices)) => devices.filter_map(|device| device.ok())
+            .find(|(_, info)| info.name == name.as_str() && info.max_input_channels > 0)
+            .map(|(index, _)| index),
+        _ => None
+    };
+    let mic_index = match named_mic_index {
+        Some(index) => index,
+        None => pa.default_input_device().expect("Unable to get default device")
+    };
+    let mic = pa.device_info(mic_index).expect("Unable to get mic info"); 
+
+    let input_stream_params = portaudio::StreamParameters::<f32>::new(mic_index, 1, true, mic.default_low_input_latency);
+    let input_stream_settings = portaudio::InputStreamSettings::new(input_stream_params, mic.default_sample_rate, ANALYSIS_BUFFER as u32);
+
+    let mut stream = pa.open_non_blocking_stream(input_stream_settings, move |portaudio::InputStreamCallbackArgs {buffer, ..}| {
+        //samples vs signal?? for namin variables sake what am I sending? (seems like a sample = frame of audio = signal)
+        match mic_sender.send(buffer) {
+            Ok(_) => portaudio::Continue,
+            Err(_) => portaudio::Complete
+        }
+    }).expect("Unable to open stream");
+
+    println!("Starting audio stream...");
+    stream.start().expect("Unable to start stream"); 
+    return stream;
+}
+
+//every game that gets torn down is recorded, also when it's restarted or the window is closed halfway through
+fn record_score(store: &Ecs) {
+    let mut players: Vec<EntityId> = Vec::new();
+    store.collect_with(&recs::component_filter!(Score), &mut players);
+    if !players.is_empty() {
+        highscore::record(store.get::<Score>(players[0]).unwrap().total, difficulty::recorded(store));
+    }
+}
+
+//the game is over, its score is recorded and the scene is swapped for the results
+fn finish(window: &mut three::Window, store: &mut Ecs, menu: &mut three::Text, title: &str) -> Vec<three::Mesh> {
+    record_score(store);
+
+    menu.set_text(system::statistics::results_text(store, title));
+    let timeline = system::statistics::timeline(store);
+
+    window.scene = window.factory.scene();
+    window.scene.add(&*menu);
+    *store = Ecs::new();
+    return factory::create_score_graph(window, &timeline);
 }
 
 fn main() {
+    let mut configured = settings::load();
+    configured.difficulty = difficulty::from_args(configured.difficulty);
+
     let mut window_builder = three::Window::builder("INSYNC");
-    window_builder.fullscreen(true); 
+    window_builder.fullscreen(configured.fullscreen); 
     let mut window = window_builder.build();
 
     let camera = window.factory.perspective_camera(75.0, 1.0 .. 30.0);
     camera.set_position([0.0, 0.0, 10.0]);
 
+    let track_directory = track::directory_from_args();
+    let pa = portaudio::PortAudio::new().expect("Unable to open PortAudio"); 
+    let mut session = Session {
+        settings: configured,
+        input_devices: input_devices(&pa),
+        tracks: track::list(&track_directory),
+        track_directory: track_directory,
+        track: None,
+        audio: None,
+        duration: util::argument("--session").and_then(|seconds| seconds.parse::<f32>().ok())
+    };
+    let estimates = track::estimate_in_background(&session.tracks);
+    //the game starts on the title screen, the store is filled once a game is started from there
     let mut store = Ecs::new();
-    factory::create_tunnel(&mut window);
-    factory::create_player(&mut window, &mut store);
+    let mut playback: Option<Playback> = None;
     
 
-    let (sender, receiver): (SyncSender<Vec<Position>>, Receiver<Vec<Position>>) = sync_channel(1);
+    let (sender, receiver): (SyncSender<formation::FormationParameters>, Receiver<formation::FormationParameters>) = sync_channel(1);
     let mut enemy_scheduler = Scheduler::new();
     
     enemy_scheduler.every(2.seconds()).run(move || {
@@ -75,54 +474,214 @@ fn main() {
             }
         });
 
-    let pa = portaudio::PortAudio::new().expect("Unable to open PortAudio"); 
-    let default_mic_index = pa.default_input_device().expect("Unable to get default device"); 
-    let mic = pa.device_info(default_mic_index).expect("Unable to get mic info"); 
+    let (mic_sender, mic_receiver): (Sender<&'static [f32]>, Receiver<&'static [f32]>) = channel();
+    let mut stream = open_microphone(&pa, &session.settings.input_device, mic_sender.clone());
+    //the device the stream was opened with, the stream is reopened when the settings pick another one
+    let mut stream_device = session.settings.input_device.clone();
 
-    let input_stream_params = portaudio::StreamParameters::<f32>::new(default_mic_index, 1, true, mic.default_low_input_latency);
-    let input_stream_settings = portaudio::InputStreamSettings::new(input_stream_params, mic.default_sample_rate, 256);
+    let mut audio_history = new_audio_history();
 
-    let (mic_sender, mic_receiver): (Sender<&[f32]>, Receiver<&[f32]>) = channel();
+    let mut events: Vec<GameEvent> = Vec::new();
+    let mut commands = CommandBuffer {
+        despawn: Vec::new()
+    };
+    let clock = window.input.time();
+    let mut rhythm = Rhythm {
+        now: 0.0,
+        last_beat: 0.0,
+        interval: 0.5,
+        beats: 0
+    };
+    let mut encounter = Encounter {
+        intense_since: None,
+        calm_since: None,
+        next_boss: 0.0
+    };
+    let mut damage_feedback = factory::create_damage_feedback(&mut window, session.settings.volume);
+    let mut gamepads = gamepad::connect();
+    let mut state = GameState::Title{ selected: 0 };
+    let mut menu = factory::create_menu(&mut window);
+    window.scene.add(&menu);
+    //the bars of the score graph only live as long as the results screen
+    let mut score_graph: Vec<three::Mesh> = Vec::new();
+    //time spent paused is taken off the clock so nothing expires or moves while the game is frozen
+    let mut paused_for: f32 = 0.0;
 
-    let mut stream = pa.open_non_blocking_stream(input_stream_settings, move |portaudio::InputStreamCallbackArgs {buffer, ..}| {
-        //samples vs signal?? for namin variables sake what am I sending? (seems like a sample = frame of audio = signal)
-        match mic_sender.send(buffer) {
-            Ok(_) => portaudio::Continue,
-            Err(_) => portaudio::Complete
+    while window.update() {
+        let now = clock.elapsed(&window.input) - paused_for;
+        let was_playing = state == GameState::Playing;
+        track::receive_estimates(&mut session.tracks, &estimates);
+        let mut controls = action::read(&window, &session.settings.bindings);
+        gamepad::read(&mut gamepads, &mut controls);
+        match system::gamestate::run(&mut window, &controls, &mut state, &mut session, &mut menu, now) {
+            Some(MenuAction::Resume) => {
+                paused_for = paused_for + system::gamestate::resume(&mut window, &mut state, &menu, now);
+                if let Some(ref playback) = playback {
+                    playback.source.resume();
+                }
+            },
+            Some(MenuAction::Play) | Some(MenuAction::Restart) => {
+                system::gamestate::resume(&mut window, &mut state, &menu, now);
+                if let Some(ref playback) = playback {
+                    playback.source.stop();
+                }
+                record_score(&store);
+                //a new scene drops every mesh and text of the old game at once
+                window.scene = window.factory.scene();
+                let (new_store, new_playback) = start(&mut window, &session);
+                store = new_store;
+                playback = new_playback;
+                rhythm = Rhythm {
+                    now: 0.0,
+                    last_beat: 0.0,
+                    interval: 0.5,
+                    beats: 0
+                };
+                encounter = Encounter {
+                    intense_since: None,
+                    calm_since: None,
+                    next_boss: 0.0
+                };
+                //the averages of the last song would make the start of this one look quiet or loud
+                audio_history = new_audio_history();
+                damage_feedback = factory::create_damage_feedback(&mut window, session.settings.volume);
+                commands.despawn.clear();
+                paused_for = clock.elapsed(&window.input);
+                continue;
+            },
+            Some(MenuAction::ApplySettings) => {
+                if let Some(ref mut playback) = playback {
+                    playback.source.set_volume(session.settings.volume);
+                }
+                damage_feedback.source.set_volume(session.settings.volume);
+                //a game in progress picks up the new difficulty right away, only the lives stay as they are
+                let mut difficulties: Vec<EntityId> = Vec::new();
+                store.collect_with(&recs::component_filter!(Difficulty), &mut difficulties);
+                for entity in difficulties.iter() {
+                    let recorded = store.get::<Difficulty>(*entity).unwrap().recorded;
+                    let _ = store.set(*entity, Difficulty{preset: session.settings.difficulty, recorded: difficulty::easier(recorded, session.settings.difficulty)});
+                }
+                let monitor = if session.settings.fullscreen { Some(window.glutin_window().get_current_monitor()) } else { None };
+                window.glutin_window().set_fullscreen(monitor);
+            },
+            Some(MenuAction::SaveSettings) => {
+                settings::save(&session.settings);
+                //the microphone is only reopened once, not for every device stepped past on the way
+                if stream_device != session.settings.input_device {
+                    let _ = stream.stop();
+                    let _ = stream.close();
+                    stream = open_microphone(&pa, &session.settings.input_device, mic_sender.clone());
+                    stream_device = session.settings.input_device.clone();
+                }
+            },
+            Some(MenuAction::BackToTitle) => {
+                score_graph.clear();
+                window.scene = window.factory.scene();
+                window.scene.add(&menu);
+            },
+            Some(MenuAction::Quit) => break,
+            None => ()
         }
-    }).expect("Unable to open stream");
 
-    let mut audio_history = AudioHistory {
-        spectrum: VecDeque::new(),
-        novelty: VecDeque::new(),
-        normalised_novelty: VecDeque::new(),
-        last_peak: 0.0
-    };
+        if was_playing && state != GameState::Playing {
+            if let Some(ref playback) = playback {
+                playback.source.pause();
+            }
+        }
 
-    println!("Starting audio stream...");
-    stream.start().expect("Unable to start stream"); 
+        //the microphone keeps recording in the menus and during tracks, its samples are only analysed when it is the input
+        //it delivers buffers faster than frames are drawn, everything but the latest buffer is dropped so the analysis never lags behind
+        let mic_samples = mic_receiver.try_iter().last();
+        if state != GameState::Playing {
+            window.render(&camera);
+            continue;
+        }
 
-    while window.update() {
-        match mic_receiver.try_recv() {
-            Ok(samples) => system::audio_analysis::calculate_novelty_curve(samples, &mut audio_history),
-            Err(_) => ()
+        rhythm.now = now;
+
+        match playback {
+            Some(ref playback) => match track::buffer_at(&playback.audio, rhythm.now, ANALYSIS_BUFFER) {
+                Some(buffer) => system::audio_analysis::calculate_novelty_curve(buffer, &mut audio_history),
+                None => ()
+            },
+            None => match mic_samples {
+                Some(samples) => system::audio_analysis::calculate_novelty_curve(samples, &mut audio_history),
+                None => ()
+            }
         }
+        let intensity = system::audio_analysis::intensity(&audio_history);
 
         //NOTE: spawn enemies on beat, cloud use some improvement
         //small improvements can already be made by removing dependencies/comstraints imposed on this by the enemy schedueler
         //figuering out how to pick a beat (1/4, 1/8, 1/16) would be the killer feature
-        match system::audio_analysis::peak_detection(&mut audio_history) {
+        //a beatmap says where the beats are, otherwise they are detected from the peaks
+        let beatmap = playback.as_ref().and_then(|playback| playback.beatmap);
+        if let Some(ref beatmap) = beatmap {
+            if system::rhythm::mapped_beat(&mut rhythm, beatmap) {
+                events.push(GameEvent::Beat);
+            }
+        }
+
+        match system::audio_analysis::peak_detection(&mut audio_history, difficulty::current(&store).peak_threshold) {
             None => (),
-            Some(_peak) => system::enemy_spawn::run(&mut window, &mut store, &mut enemy_scheduler, &receiver)
+            Some(peak) => {
+                if beatmap.is_none() {
+                    system::rhythm::beat(&mut rhythm, session.settings.latency);
+                    events.push(GameEvent::Beat);
+                }
+                system::pickup::spawn(&mut window, &mut store, peak);
+                //the boss brings its own minions
+                if !system::boss::is_active(&store) {
+                    system::enemy_spawn::run(&mut window, &mut store, &mut enemy_scheduler, &receiver, peak, audio_history.spectral_centroid, intensity);
+                }
+            }
+        }
+
+        system::input::run(&mut store, &controls);
+        system::weapon::run(&mut window, &mut store, &controls, &rhythm, &mut events);
+        system::enemy_fire::run(&mut window, &mut store, &rhythm, &events);
+        system::position::run(&mut store, &rhythm, intensity, &events);
+        system::collision::run(&mut store, &rhythm, &mut events, &mut commands); 
+        system::damage_feedback::run(&mut store, &camera, &rhythm, &mut damage_feedback, session.settings.visual_intensity, &events);
+        system::enemy_spawn::split(&mut window, &mut store, &events);
+        system::boss::run(&mut window, &mut store, &audio_history, &rhythm, &mut encounter, &mut events, &mut commands);
+        system::combo::run(&mut store, &events);
+        system::score::run(&mut store, &events); 
+        system::health::run(&mut store, &events);
+        system::pickup::run(&mut store, &rhythm, &events);
+        system::rhythm::run(&mut store, &events);
+        system::garbage_collection::run(&mut window, &mut store, &rhythm, &mut events, &mut commands);
+        system::statistics::run(&mut store, &rhythm, &events);
+        events.clear();
+
+        //a track is over once there is nothing left to analyse, the microphone only stops when a session length is set
+        let cleared = match playback {
+            Some(ref playback) => track::buffer_at(&playback.audio, rhythm.now, ANALYSIS_BUFFER).is_none(),
+            None => session.duration.map(|duration| rhythm.now >= duration).unwrap_or(false)
+        };
+
+        let ending = if system::health::is_game_over(&store) {
+            Some("game over".to_string())
+        } else if cleared {
+            Some(format!("cleared\nclear bonus: {}", system::score::clear_bonus(&mut store)))
+        } else {
+            None
+        };
+
+        if let Some(title) = ending {
+            if let Some(ref playback) = playback {
+                playback.source.stop();
+            }
+            playback = None;
+            //the camera might still be shaking from the last hit
+            camera.set_position([0.0, 0.0, 10.0]);
+            score_graph = finish(&mut window, &mut store, &mut menu, &title);
+            state = GameState::Results;
         }
 
-        system::input::run(&mut window, &mut store);
-        system::position::run(&mut store);
-        system::collision::run(&mut window, &mut store); 
-        system::score::run(&mut store); 
-        system::health::run(&mut store);
-        system::garbage_collection::run(&mut window, &mut store);
         window.render(&camera);
     }
+
+    record_score(&store);
 }
 
@@ -0,0 +1,90 @@
+//settings are kept in a plain text file with one `<name> = <value>` pair per line, unknown or broken lines are skipped
+
+use std::fs;
+use crate::*;
+
+const PATH: &str = "./settings.cfg";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    //0.0 is silent and 1.0 is full volume
+    pub volume: f32,
+    //name of the microphone, the system default is used when it is none or can't be found
+    pub input_device: Option<String>,
+    //seconds between the music and its detection, detected beats are moved back by this much, never negative
+    pub latency: f32,
+    pub difficulty: &'static difficulty::Preset,
+    pub fullscreen: bool,
+    //scales effects like the screen shake, 0.0 turns them off
+    pub visual_intensity: f32,
+    pub bindings: Vec<action::Binding>
+}
+
+pub fn default() -> Settings {
+    return Settings {
+        volume: 1.0,
+        input_device: None,
+        latency: 0.0,
+        difficulty: &difficulty::NORMAL,
+        fullscreen: true,
+        visual_intensity: 1.0,
+        bindings: action::default_bindings()
+    };
+}
+
+pub fn load() -> Settings {
+    let mut settings = default();
+    let contents = match fs::read_to_string(PATH) {
+        Ok(contents) => contents,
+        Err(_) => return settings
+    };
+
+    for line in contents.lines() {
+        let mut parts = line.splitn(2, '=');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name.trim(), value.trim()),
+            _ => continue
+        };
+
+        match name {
+            "volume" => settings.volume = value.parse().unwrap_or(settings.volume),
+            "input_device" => settings.input_device = if value.is_empty() { None } else { Some(value.to_string()) },
+            "latency" => settings.latency = value.parse::<f32>().unwrap_or(settings.latency).max(0.0),
+            "difficulty" => settings.difficulty = difficulty::from_name(value).unwrap_or(settings.difficulty),
+            "fullscreen" => settings.fullscreen = value.parse().unwrap_or(settings.fullscreen),
+            "visual_intensity" => settings.visual_intensity = value.parse().unwrap_or(settings.visual_intensity),
+            //bindings are written as `bind.<action> = <key>, <key>`, unknown keys are dropped
+            _ if name.starts_with("bind.") => {
+                let keys: Vec<three::Key> = value.split(',').filter_map(|key| action::key_from_name(key.trim())).collect();
+                let binding = settings.bindings.iter_mut().find(|binding| Some(binding.action) == action::from_name(&name["bind.".len()..]));
+                match binding {
+                    Some(binding) if !keys.is_empty() => binding.keys = keys,
+                    _ => ()
+                }
+            },
+            _ => ()
+        }
+    }
+
+    return settings;
+}
+
+pub fn save(settings: &Settings) {
+    let mut contents = format!(
+        "volume = {}\ninput_device = {}\nlatency = {}\ndifficulty = {}\nfullscreen = {}\nvisual_intensity = {}\n",
+        settings.volume,
+        settings.input_device.clone().unwrap_or_default(),
+        settings.latency,
+        settings.difficulty.name,
+        settings.fullscreen,
+        settings.visual_intensity
+    );
+    for binding in settings.bindings.iter() {
+        let keys: Vec<String> = binding.keys.iter().map(|key| action::key_name(*key)).collect();
+        contents = format!("{}bind.{} = {}\n", contents, action::name(binding.action), keys.join(", "));
+    }
+
+    if let Err(err) = fs::write(PATH, contents) {
+        println!("[settings]: unable to save {}. {:?}", PATH, err);
+    }
+}
\ No newline at end of file
@@ -1,10 +1,37 @@
 use crate::*;
 
+//smoothing factors of the running averages, roughly one second and fifteen seconds at 60 updates per second
+const SHORT_WINDOW: f64 = 0.02;
+const LONG_WINDOW: f64 = 0.001;
+//buffers to analyse before the long averages are trusted, about five seconds
+const WARM_UP: i32 = 300;
+//bounds of the intensity, keeps a silent intro playable and a drop from becoming impossible
+const MIN_INTENSITY: f32 = 0.5;
+const MAX_INTENSITY: f32 = 1.75;
+
+//the first buffers are averaged evenly so the running averages start at the level of the song instead of at zero
+fn smoothing(window: f64, count: i32) -> f64 {
+    return (1.0 / (count.max(1) as f64)).max(window);
+}
+
 pub fn calculate_novelty_curve(buffer: &[f32], history: &mut AudioHistory) {
+    history.analysed = history.analysed + 1;
     let samples: Vec<f64> = buffer.to_vec().into_iter().map(|sample| sample as f64).collect(); 
+
+    //loudness as root mean square, tracked as a fast and a slow moving average
+    let rms = (samples.iter().fold(0.0, |sum, sample| sum + sample * sample) / (samples.len().max(1) as f64)).sqrt();
+    history.short_energy = history.short_energy + (rms - history.short_energy) * smoothing(SHORT_WINDOW, history.analysed);
+    history.long_energy = history.long_energy + (rms - history.long_energy) * smoothing(LONG_WINDOW, history.analysed);
     //Fourier Transform, note that the output is in "nyquist bin" not "Hz"!
     let spectrum = meyda::get_amp_spectrum(&samples);
 
+    //spectral centroid, normalised by the number of bins
+    let magnitude = spectrum.iter().fold(0.0, |sum, sample| sum + sample);
+    if magnitude > 0.0 {
+        let weighted = spectrum.iter().enumerate().fold(0.0, |sum, (bin, sample)| sum + (bin as f64) * sample);
+        history.spectral_centroid = (weighted / magnitude) / (spectrum.len() as f64);
+    }
+
     //Log compression
     // Y = log( 1 + C * |X|) 
     let log_spectrum: Vec<f64> = spectrum.into_iter().map(|sample| {
@@ -33,6 +60,9 @@ pub fn calculate_novelty_curve(buffer: &[f32], history: &mut AudioHistory) {
     //accumulation into novelty point
     let novelty_point = differentiation.iter().fold(0.0, |sum, difference| sum + difference);
     history.novelty.push_front(novelty_point);
+    //there is no novelty for the very first buffer
+    history.short_novelty = history.short_novelty + (novelty_point - history.short_novelty) * smoothing(SHORT_WINDOW, history.analysed - 1);
+    history.long_novelty = history.long_novelty + (novelty_point - history.long_novelty) * smoothing(LONG_WINDOW, history.analysed - 1);
 
     if history.novelty.len() < 128 //76 
     { //novelty history length treshold
@@ -60,7 +90,7 @@ pub fn calculate_novelty_curve(buffer: &[f32], history: &mut AudioHistory) {
 }
 
 //return Err no peak and Ok deteced peak 
-pub fn peak_detection(audio_history: &mut AudioHistory) -> Option<f64> {
+pub fn peak_detection(audio_history: &mut AudioHistory, threshold: f64) -> Option<f64> {
     if audio_history.normalised_novelty.len() > 2 {
 
         for (index, novelty_index) in audio_history.normalised_novelty.iter().enumerate() {
@@ -69,7 +99,7 @@ pub fn peak_detection(audio_history: &mut AudioHistory) -> Option<f64> {
                 return None; 
             }
 
-            if novelty_index > &50.0 {
+            if *novelty_index > threshold {
                 let delta = novelty_index - audio_history.last_peak; 
 
                 if delta < 1.0 && delta > -1.0 {
@@ -85,3 +115,22 @@ pub fn peak_detection(audio_history: &mut AudioHistory) -> Option<f64> {
 
     return None;
 }
+
+//how loud the song is right now compared to its long term average, quiet parts are calm and drops are frantic
+//neutral until the long average has seen enough of the song to compare against
+pub fn intensity(history: &AudioHistory) -> f32 {
+    if history.analysed < WARM_UP || history.long_energy <= 0.0 {
+        return 1.0;
+    }
+
+    let ratio = (history.short_energy / history.long_energy) as f32;
+    return ratio.max(MIN_INTENSITY).min(MAX_INTENSITY);
+}
+
+//a section like a chorus or a drop is intense when both loudness and novelty rise well above their long term average
+pub fn is_intense_section(history: &AudioHistory) -> bool {
+    return history.analysed >= WARM_UP
+        && history.long_energy > 0.0
+        && history.short_energy > history.long_energy * 1.3
+        && history.short_novelty > history.long_novelty;
+}
\ No newline at end of file
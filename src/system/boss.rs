@@ -0,0 +1,175 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//how long a section has to stay intense before the boss shows up and how long it has to stay calm before it leaves (seconds)
+const SECTION_LENGTH: f32 = 8.0;
+const CALM_LENGTH: f32 = 4.0;
+//minimum time between two bosses (seconds)
+const COOLDOWN: f32 = 60.0;
+//the boss approaches until it reaches this depth and holds its position there
+const HOLD_Z: f32 = -14.0;
+const HEALTH_BAR_LENGTH: i32 = 20;
+//a lunge takes about a second at 60 updates per second
+const LUNGE_SPEED: f32 = 0.02;
+
+pub fn is_active(store: &recs::Ecs) -> bool {
+    let mut bosses: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Boss), &mut bosses);
+    return !bosses.is_empty();
+}
+
+fn track_section(audio_history: &AudioHistory, rhythm: &Rhythm, encounter: &mut Encounter) {
+    if system::audio_analysis::is_intense_section(audio_history) {
+        encounter.calm_since = None;
+        if encounter.intense_since.is_none() {
+            encounter.intense_since = Some(rhythm.now);
+        }
+    } else {
+        encounter.intense_since = None;
+        if encounter.calm_since.is_none() {
+            encounter.calm_since = Some(rhythm.now);
+        }
+    }
+}
+
+fn spawn_minions(window: &mut three::Window, store: &mut recs::Ecs, boss: &Boss, shape: formation::Formation, archetype: &'static archetype::Archetype) {
+    let parameters = formation::FormationParameters {
+        count: 8,
+        radius: 3.0,
+        z: boss.anchor.z + 2.0,
+        rotation: boss.rotation
+    };
+
+    for (position, movement) in formation::build(shape, &parameters).iter() {
+        factory::create_enemy(window, store, *position, *movement, archetype);
+    }
+}
+
+//every fourth beat the boss attacks, cycling through its patterns
+fn attack(window: &mut three::Window, store: &mut recs::Ecs, boss: &mut Boss) {
+    boss.beats = boss.beats + 1;
+    if boss.beats % 4 != 0 {
+        return;
+    }
+
+    match (boss.beats / 4) % 3 {
+        0 => spawn_minions(window, store, boss, formation::Formation::Ring, &archetype::DRONE),
+        1 => boss.lunge = LUNGE_SPEED,
+        _ => spawn_minions(window, store, boss, formation::Formation::Spiral, &archetype::DART)
+    }
+}
+
+fn health_bar(boss: &Boss) -> String {
+    let filled = ((boss.health.max(0) * HEALTH_BAR_LENGTH) as f32 / boss.max_health as f32).ceil() as usize;
+    let empty = (HEALTH_BAR_LENGTH as usize) - filled;
+    return format!("BOSS [{}{}]", "|".repeat(filled), " ".repeat(empty));
+}
+
+fn remove(window: &mut three::Window, store: &mut recs::Ecs, entity: EntityId, boss: &Boss, commands: &mut CommandBuffer) {
+    let mut parts: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(BossPart), &mut parts);
+
+    for part in parts.iter() {
+        if store.get::<BossPart>(*part).unwrap().boss == entity {
+            util::despawn(*part, commands);
+        }
+    }
+
+    //the boss itself has no mesh so it doesn't go through the command buffer
+    window.scene.remove(&boss.ui);
+    let _ = store.destroy_entity(entity);
+}
+
+pub fn run(window: &mut three::Window, store: &mut recs::Ecs, audio_history: &AudioHistory, rhythm: &Rhythm, encounter: &mut Encounter, events: &mut Vec<GameEvent>, commands: &mut CommandBuffer) {
+    track_section(audio_history, rhythm, encounter);
+
+    let mut bosses: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Boss), &mut bosses);
+
+    if bosses.is_empty() {
+        let sustained = match encounter.intense_since {
+            Some(since) => rhythm.now - since > SECTION_LENGTH,
+            None => false
+        };
+
+        if sustained && rhythm.now > encounter.next_boss {
+            factory::create_boss(window, store);
+        }
+
+        return;
+    }
+
+    let entity = bosses[0];
+    let mut boss = store.get::<Boss>(entity).unwrap();
+    let on_beat = events.iter().any(|event| *event == GameEvent::Beat);
+
+    for event in events.iter() {
+        match event {
+            GameEvent::BossHit{ boss: hit, damage, .. } if *hit == entity => boss.health = boss.health - damage,
+            _ => ()
+        }
+    }
+
+    match encounter.calm_since {
+        Some(since) if rhythm.now - since > CALM_LENGTH => boss.retreating = true,
+        _ => ()
+    }
+
+    if boss.health <= 0 {
+        events.push(GameEvent::BossDefeated{ position: boss.anchor });
+        remove(window, store, entity, &boss, commands);
+        encounter.next_boss = rhythm.now + COOLDOWN;
+        return;
+    }
+
+    //the section ended before the boss was defeated, it leaves back down the tunnel
+    if boss.retreating && boss.anchor.z < -35.0 {
+        remove(window, store, entity, &boss, commands);
+        encounter.next_boss = rhythm.now + COOLDOWN;
+        return;
+    }
+
+    if boss.retreating {
+        boss.anchor.z = boss.anchor.z - 0.1;
+    } else if boss.anchor.z < HOLD_Z {
+        boss.anchor.z = boss.anchor.z + 0.05;
+    } else if on_beat {
+        attack(window, store, &mut boss);
+    }
+
+    //spins faster once it's badly damaged
+    let enraged = boss.health * 3 < boss.max_health;
+    boss.rotation = (boss.rotation + if enraged { 1.5 } else { 0.6 }) % 360.0;
+    if boss.lunge > 0.0 {
+        boss.lunge = boss.lunge + LUNGE_SPEED;
+        if boss.lunge >= 1.0 {
+            boss.lunge = 0.0;
+        }
+    }
+
+    let mut parts: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(BossPart, GameObject), &mut parts);
+
+    for part_entity in parts.iter() {
+        let part = store.get::<BossPart>(*part_entity).unwrap();
+        if part.boss != entity {
+            continue;
+        }
+
+        let old_position = store.get::<Position>(*part_entity).unwrap();
+        let offset = util::polar_to_cartesian(part.radius, part.angle + boss.rotation);
+        //the arms swing forward through the player's plane at z 0.0 and back, the core stays at the anchor
+        let lunge = if part.radius > 0.0 { (boss.lunge * std::f32::consts::PI).sin() * -boss.anchor.z } else { 0.0 };
+        let new_position = Position{ x: boss.anchor.x + offset[0], y: boss.anchor.y + offset[1], z: boss.anchor.z + lunge };
+
+        let _ = store.set(*part_entity, new_position);
+        let _ = store.set(*part_entity, Displacement{
+            x: new_position.x - old_position.x,
+            y: new_position.y - old_position.y,
+            z: new_position.z - old_position.z
+        });
+    }
+
+    boss.ui.set_text(health_bar(&boss));
+    let _ = store.set(entity, boss);
+}
\ No newline at end of file
@@ -1,17 +1,230 @@
+use std::cmp::Ordering;
 use recs::{EntityId, component_filter};
 use crate::*; 
 
-pub fn run(mut window: &mut three::Window, mut store: &mut recs::Ecs) {
+//seconds the player can't be hurt after a hit
+const INVULNERABILITY: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug)]
+struct BoundingBox {
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+    y_max: f32,
+    z_min: f32,
+    z_max: f32
+}
+
+impl BoundingBox {
+    fn intersects(&self, other: &BoundingBox) -> bool {
+        return self.x_min < other.x_max && self.x_max > other.x_min
+            && self.y_min < other.y_max && self.y_max > other.y_min
+            && self.z_min < other.z_max && self.z_max > other.z_min;
+    }
+
+    //the volume covered while moving from the previous frame to the current one
+    fn swept(&self, displacement: &Displacement) -> BoundingBox {
+        return BoundingBox{
+            x_min: self.x_min.min(self.x_min - displacement.x),
+            x_max: self.x_max.max(self.x_max - displacement.x),
+            y_min: self.y_min.min(self.y_min - displacement.y),
+            y_max: self.y_max.max(self.y_max - displacement.y),
+            z_min: self.z_min.min(self.z_min - displacement.z),
+            z_max: self.z_max.max(self.z_max - displacement.z)
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Collider {
+    entity: EntityId,
+    position: Position,
+    bounds: BoundingBox,
+    displacement: Displacement
+}
+
+impl Collider {
+    fn swept_bounds(&self) -> BoundingBox {
+        return self.bounds.swept(&self.displacement);
+    }
+}
+
+//continuous collision: the ray from where `moving` was last frame to where it is now (relative to `target`)
+//is cast against `target` grown by the half extents of `moving`, so fast projectiles can't skip over an enemy between frames
+fn swept_intersects(moving: &Collider, target: &Collider) -> bool {
+    if moving.bounds.intersects(&target.bounds) {
+        return true;
+    }
+
+    let half_extents = [
+        (moving.bounds.x_max - moving.bounds.x_min) / 2.0,
+        (moving.bounds.y_max - moving.bounds.y_min) / 2.0,
+        (moving.bounds.z_max - moving.bounds.z_min) / 2.0
+    ];
+    let end = [
+        moving.bounds.x_min + half_extents[0],
+        moving.bounds.y_min + half_extents[1],
+        moving.bounds.z_min + half_extents[2]
+    ];
+    let delta = [
+        moving.displacement.x - target.displacement.x,
+        moving.displacement.y - target.displacement.y,
+        moving.displacement.z - target.displacement.z
+    ];
+    //the target is moved back to where it was last frame, the ray starts there as well
+    let target_min = [
+        target.bounds.x_min - target.displacement.x - half_extents[0],
+        target.bounds.y_min - target.displacement.y - half_extents[1],
+        target.bounds.z_min - target.displacement.z - half_extents[2]
+    ];
+    let target_max = [
+        target.bounds.x_max - target.displacement.x + half_extents[0],
+        target.bounds.y_max - target.displacement.y + half_extents[1],
+        target.bounds.z_max - target.displacement.z + half_extents[2]
+    ];
+    let start = [
+        end[0] - moving.displacement.x,
+        end[1] - moving.displacement.y,
+        end[2] - moving.displacement.z
+    ];
+
+    let mut t_enter: f32 = 0.0;
+    let mut t_exit: f32 = 1.0;
+    for axis in 0..3 {
+        if delta[axis].abs() < std::f32::EPSILON {
+            if start[axis] <= target_min[axis] || start[axis] >= target_max[axis] {
+                return false;
+            }
+
+            continue;
+        }
+
+        let t_a = (target_min[axis] - start[axis]) / delta[axis];
+        let t_b = (target_max[axis] - start[axis]) / delta[axis];
+        t_enter = t_enter.max(t_a.min(t_b));
+        t_exit = t_exit.min(t_a.max(t_b));
+
+        if t_enter > t_exit {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+//broad-phase: sweep and prune along the z-axis, the axis everything travels along through the tunnel.
+//returns index pairs (a, b) of which the swept z-intervals overlap, the narrow-phase still has to check x and y.
+fn sweep_and_prune(group_a: &[Collider], group_b: &[Collider]) -> Vec<(usize, usize)> {
+    //the swept bounds are built once per collider instead of in every comparison
+    let bounds_a: Vec<BoundingBox> = group_a.iter().map(|collider| collider.swept_bounds()).collect();
+    let bounds_b: Vec<BoundingBox> = group_b.iter().map(|collider| collider.swept_bounds()).collect();
+    let sort_by_z_min = |bounds: &[BoundingBox]| {
+        let mut order: Vec<usize> = (0..bounds.len()).collect();
+        order.sort_by(|a, b| bounds[*a].z_min.partial_cmp(&bounds[*b].z_min).unwrap_or(Ordering::Equal));
+        return order;
+    };
+
+    let order_a = sort_by_z_min(&bounds_a);
+    let order_b = sort_by_z_min(&bounds_b);
+    let mut active_a: Vec<usize> = Vec::new();
+    let mut active_b: Vec<usize> = Vec::new();
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    let mut next_a = 0;
+    let mut next_b = 0;
+
+    while next_a < order_a.len() || next_b < order_b.len() {
+        let take_a = next_b >= order_b.len()
+            || (next_a < order_a.len() && bounds_a[order_a[next_a]].z_min <= bounds_b[order_b[next_b]].z_min);
+
+        //everything that ends before the current interval starts can never overlap with anything that follows
+        if take_a {
+            let index = order_a[next_a];
+            let z_min = bounds_a[index].z_min;
+            active_b.retain(|other| bounds_b[*other].z_max > z_min);
+            for other in active_b.iter() {
+                pairs.push((index, *other));
+            }
+            active_a.push(index);
+            next_a = next_a + 1;
+        } else {
+            let index = order_b[next_b];
+            let z_min = bounds_b[index].z_min;
+            active_a.retain(|other| bounds_a[*other].z_max > z_min);
+            for other in active_a.iter() {
+                pairs.push((*other, index));
+            }
+            active_b.push(index);
+            next_b = next_b + 1;
+        }
+    }
+
+    return pairs;
+}
+
+//uses up a projectile on a target and returns its damage
+//piercing projectiles keep going but hit every target only once, None means this target was already hit
+fn spend_projectile(store: &mut recs::Ecs, bullet: EntityId, target: EntityId, events: &mut Vec<GameEvent>, commands: &mut CommandBuffer) -> Option<i32> {
+    let mut projectile = store.get::<Projectile>(bullet).unwrap();
+
+    if !projectile.piercing {
+        util::despawn(bullet, commands);
+        events.push(GameEvent::ProjectileHit);
+        return Some(projectile.damage);
+    }
+
+    if projectile.hits.contains(&target) {
+        return None;
+    }
+
+    if projectile.hits.is_empty() {
+        events.push(GameEvent::ProjectileHit);
+    }
+
+    projectile.hits.push(target);
+    let damage = projectile.damage;
+    let _ = store.set(bullet, projectile);
+    return Some(damage);
+}
+
+//a shield takes the hit instead of the player, either way the player can't be hurt again for a moment
+//this also keeps overlapping enemies from draining several lives in a single frame
+fn player_hit(store: &mut recs::Ecs, rhythm: &Rhythm, position: Position, events: &mut Vec<GameEvent>) {
+    let mut players: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Health), &mut players);
+    if players.is_empty() {
+        return;
+    }
+
+    let mut health = store.get::<Health>(players[0]).unwrap();
+    if rhythm.now < health.invulnerable_until {
+        return;
+    }
+    health.invulnerable_until = rhythm.now + INVULNERABILITY;
+    let _ = store.set(players[0], health);
+
+    let shielded = store.get::<PowerUps>(players[0]).map(|powerups| powerups.shield).unwrap_or(false);
+    if shielded {
+        events.push(GameEvent::ShieldBroken);
+        return;
+    }
+
+    events.push(GameEvent::PlayerHit{ position: position });
+}
+
+pub fn run(store: &mut recs::Ecs, rhythm: &Rhythm, events: &mut Vec<GameEvent>, commands: &mut CommandBuffer) {
     //NOTE: usage of skeletons would be nice since meshes can be used and the game can move away from basic shapes
     //NOTE: in the next iteration dimentions should at least be precalculated from the vertices in the base shape. 
 
     let component_filter = component_filter!(Position, GameObject);
     let mut entities: Vec<EntityId> = Vec::new(); 
-    // (EntityId, minX, maxX, minY, maxY, minZ, maxZ)
-    let mut enemies: Vec<(&EntityId, f32, f32, f32, f32, f32, f32)> = Vec::new(); 
-    let mut bullets: Vec<(&EntityId, f32, f32, f32, f32, f32, f32)> = Vec::new(); 
-    let mut player = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
-    
+    let mut enemies: Vec<Collider> = Vec::new();
+    let mut bullets: Vec<Collider> = Vec::new();
+    let mut boss_parts: Vec<Collider> = Vec::new();
+    let mut pickups: Vec<Collider> = Vec::new();
+    let mut hostile_bullets: Vec<Collider> = Vec::new();
+    let mut player = BoundingBox{ x_min: 0.0, x_max: 0.0, y_min: 0.0, y_max: 0.0, z_min: 0.0, z_max: 0.0 };
+    let mut player_collider: Option<Collider> = None;
+
     store.collect_with(&component_filter, &mut entities);
         let find_min = |min, current| {
             if current < min {
@@ -32,7 +245,8 @@ pub fn run(mut window: &mut three::Window, mut store: &mut recs::Ecs) {
     for entity in entities.iter() {
         let gameobject = store.get::<GameObject>(*entity).unwrap(); 
         let position = store.get::<Position>(*entity).unwrap(); 
-  
+        let displacement = store.get::<Displacement>(*entity).unwrap_or(Displacement{ x: 0.0, y: 0.0, z: 0.0 });
+
         let x_values = gameobject.vertices.iter().map(|vertex| vertex.x );
         let x_min = x_values.clone().fold(0.0, find_min); 
         let x_max = x_values.clone().fold(0.0, find_max);
@@ -45,50 +259,247 @@ pub fn run(mut window: &mut three::Window, mut store: &mut recs::Ecs) {
         let z_min = z_values.clone().fold(0.0, find_min); 
         let z_max = z_values.clone().fold(0.0, find_max);
 
+        let bounds = BoundingBox{
+            x_min: x_min + position.x,
+            x_max: x_max + position.x,
+            y_min: y_min + position.y,
+            y_max: y_max + position.y,
+            z_min: z_min + position.z,
+            z_max: z_max + position.z
+        };
+
         match gameobject.object_type {
-            GameObjectType::Enemy => enemies.push((entity, x_min + position.x, x_max + position.x, y_min + position.y, y_max + position.y, z_min + position.z, z_max + position.z)),
-            GameObjectType::Player => player = (x_min + position.x, x_max + position.x, y_min + position.y, y_max + position.y, z_min + position.z, z_max + position.z),
-            GameObjectType::Bullet => bullets.push((entity, x_min + position.x, x_max + position.x, y_min + position.y, y_max + position.y, z_min + position.z, z_max + position.z)),
+            GameObjectType::Enemy => enemies.push(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement }),
+            GameObjectType::Player => {
+                player = bounds;
+                player_collider = Some(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement });
+            },
+            GameObjectType::Bullet => bullets.push(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement }),
+            GameObjectType::BossPart => boss_parts.push(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement }),
+            GameObjectType::Pickup => pickups.push(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement }),
+            GameObjectType::HostileBullet => hostile_bullets.push(Collider{ entity: *entity, position: position, bounds: bounds, displacement: displacement }),
         }
     }
 
     for enemy in enemies.iter().rev() {
+        //check collision with player
+        if player.intersects(&enemy.bounds) {
+            let archetype = store.get::<Enemy>(enemy.entity).unwrap().archetype;
+            util::despawn(enemy.entity, commands);
+            player_hit(store, rhythm, enemy.position, events);
+            events.push(GameEvent::EnemyDestroyed{
+                by: GameObjectType::Player,
+                archetype: archetype,
+                judgement: None,
+                position: enemy.position,
+                beat_offset: rhythm.now - rhythm.last_beat
+            });
+        }
+    }
 
-        let (player_x_min, player_x_max, player_y_min, player_y_max, player_z_min, player_z_max) = player;
-        let (enemy_entity, enemy_x_min, enemy_x_max, enemy_y_min, enemy_y_max, enemy_z_min, enemy_z_max) = *enemy;
+    //check collision with bullets, only pairs that overlap on the z-axis are tested
+    for (enemy_index, bullet_index) in sweep_and_prune(&enemies, &bullets).into_iter().rev() {
+        let enemy = &enemies[enemy_index];
+        let bullet = &bullets[bullet_index];
 
-        //check collision with player
-        if player_x_min < enemy_x_max && player_x_max > enemy_x_min {
-            if player_y_min < enemy_y_max && player_y_max > enemy_y_min {
-                if player_z_min < enemy_z_max && player_z_max > enemy_z_min {
-                    util::remove_entity(*enemy_entity, &mut store, &mut window);
-
-                    let mut enitites: Vec<EntityId> = Vec::new(); 
-                    store.collect_with(&component_filter!(Health), &mut enitites);
-                    let mut health = store.get::<Health>(enitites[0]).unwrap();
-                    health.total = health.total - 1; 
-                    let _ = store.set(enitites[0], health);
+        //an enemy or bullet can only be used up once, even if it overlaps with multiple others this frame
+        if util::is_despawning(enemy.entity, commands) || util::is_despawning(bullet.entity, commands) {
+            continue;
+        }
+
+        if swept_intersects(bullet, enemy) {
+            let damage = match spend_projectile(store, bullet.entity, enemy.entity, events, commands) {
+                Some(damage) => damage,
+                None => continue
+            };
+
+            //armored enemies take more than one hit
+            let mut enemy_state = store.get::<Enemy>(enemy.entity).unwrap();
+            enemy_state.hit_points = enemy_state.hit_points - damage;
+            let archetype = enemy_state.archetype;
+            let _ = store.set(enemy.entity, enemy_state.clone());
+
+            if enemy_state.hit_points > 0 {
+                continue;
+            }
+
+            util::despawn(enemy.entity, commands);
+            events.push(GameEvent::EnemyDestroyed{
+                by: GameObjectType::Bullet,
+                archetype: archetype,
+                judgement: store.get::<Judgement>(bullet.entity).ok(),
+                position: enemy.position,
+                beat_offset: rhythm.now - rhythm.last_beat
+            });
+        }
+    }
+
+    //hostile bullets only ever hurt the player, they pass through enemies and the player's own bullets
+    for hostile_bullet in hostile_bullets.iter() {
+        let hit = match player_collider {
+            Some(ref player_collider) => swept_intersects(hostile_bullet, player_collider),
+            None => false
+        };
+        if hit {
+            util::despawn(hostile_bullet.entity, commands);
+            player_hit(store, rhythm, hostile_bullet.position, events);
+        }
+    }
+
+    //pickups are only collected by flying into them, bullets pass through
+    for pickup in pickups.iter() {
+        if player.intersects(&pickup.bounds) {
+            util::despawn(pickup.entity, commands);
+            events.push(GameEvent::PickupCollected{ kind: store.get::<Pickup>(pickup.entity).unwrap().kind });
+        }
+    }
+
+    //boss parts can't be destroyed on their own, they hurt the player and pass hits on to their boss
+    for part in boss_parts.iter() {
+        if player.intersects(&part.bounds) {
+            player_hit(store, rhythm, part.position, events);
+        }
+    }
+
+    for (part_index, bullet_index) in sweep_and_prune(&boss_parts, &bullets).into_iter() {
+        let part = &boss_parts[part_index];
+        let bullet = &bullets[bullet_index];
+
+        if util::is_despawning(bullet.entity, commands) {
+            continue;
+        }
+
+        if swept_intersects(bullet, part) {
+            let damage = match spend_projectile(store, bullet.entity, part.entity, events, commands) {
+                Some(damage) => damage,
+                None => continue
+            };
+
+            events.push(GameEvent::BossHit{
+                boss: store.get::<BossPart>(part.entity).unwrap().boss,
+                damage: damage,
+                judgement: store.get::<Judgement>(bullet.entity).ok(),
+                position: part.position
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use rand::Rng;
+    use super::*;
+
+    fn collider(store: &mut recs::Ecs, position: [f32; 3], size: f32, displacement: [f32; 3]) -> Collider {
+        let half = size / 2.0;
+        return Collider{
+            entity: store.create_entity(),
+            position: Position{ x: position[0], y: position[1], z: position[2] },
+            bounds: BoundingBox{
+                x_min: position[0] - half,
+                x_max: position[0] + half,
+                y_min: position[1] - half,
+                y_max: position[1] + half,
+                z_min: position[2] - half,
+                z_max: position[2] + half
+            },
+            displacement: Displacement{ x: displacement[0], y: displacement[1], z: displacement[2] }
+        };
+    }
+
+    //hundreds of enemies spread through the tunnel and a screen full of bullets flying into them
+    fn scene(store: &mut recs::Ecs) -> (Vec<Collider>, Vec<Collider>) {
+        let mut random = rand::thread_rng();
+        let mut enemies: Vec<Collider> = Vec::new();
+        let mut bullets: Vec<Collider> = Vec::new();
+        for _ in 0..400 {
+            let position = [random.gen_range(-3.0, 3.0), random.gen_range(-3.0, 3.0), random.gen_range(-100.0, 0.0)];
+            enemies.push(collider(store, position, 1.0, [0.0, 0.0, 0.1]));
+        }
+        for _ in 0..400 {
+            let position = [random.gen_range(-3.0, 3.0), random.gen_range(-3.0, 3.0), random.gen_range(-100.0, 0.0)];
+            bullets.push(collider(store, position, 0.2, [0.0, 0.0, -2.0]));
+        }
+
+        return (enemies, bullets);
+    }
+
+    #[test]
+    fn bullet_hits_enemy_it_skipped_over() {
+        let mut store = recs::Ecs::new();
+        let enemy = collider(&mut store, [0.0, 0.0, 0.0], 1.0, [0.0, 0.0, 0.0]);
+        let bullet = collider(&mut store, [0.0, 0.0, -10.0], 0.2, [0.0, 0.0, -20.0]);
+
+        assert!(!bullet.bounds.intersects(&enemy.bounds));
+        assert!(swept_intersects(&bullet, &enemy));
+    }
+
+    #[test]
+    fn bullet_misses_enemy_beside_its_path() {
+        let mut store = recs::Ecs::new();
+        let enemy = collider(&mut store, [0.0, 0.0, 0.0], 1.0, [0.0, 0.0, 0.0]);
+        let bullet = collider(&mut store, [2.0, 0.0, -10.0], 0.2, [0.0, 0.0, -20.0]);
+
+        assert!(!swept_intersects(&bullet, &enemy));
+    }
+
+    #[test]
+    fn bullet_misses_enemy_behind_it() {
+        let mut store = recs::Ecs::new();
+        let enemy = collider(&mut store, [0.0, 0.0, 0.0], 1.0, [0.0, 0.0, 0.0]);
+        let bullet = collider(&mut store, [0.0, 0.0, -10.0], 0.2, [0.0, 0.0, -5.0]);
+
+        assert!(!swept_intersects(&bullet, &enemy));
+    }
+
+    #[test]
+    fn sweep_and_prune_pairs_every_z_overlap() {
+        let mut store = recs::Ecs::new();
+        let (enemies, bullets) = scene(&mut store);
+
+        let mut expected: Vec<(usize, usize)> = Vec::new();
+        for (enemy_index, enemy) in enemies.iter().enumerate() {
+            for (bullet_index, bullet) in bullets.iter().enumerate() {
+                let enemy_bounds = enemy.swept_bounds();
+                let bullet_bounds = bullet.swept_bounds();
+                if enemy_bounds.z_min < bullet_bounds.z_max && bullet_bounds.z_min < enemy_bounds.z_max {
+                    expected.push((enemy_index, bullet_index));
                 }
             }
         }
 
-        //check collision with bullets
-        for bullet in bullets.iter().rev(){
-            let (bullet_entity, bullet_x_min, bullet_x_max, bullet_y_min, bullet_y_max, bullet_z_min, bullet_z_max) = *bullet;
-            if bullet_x_min < enemy_x_max && bullet_x_max > enemy_x_min {
-                if bullet_y_min < enemy_y_max && bullet_y_max > enemy_y_min {
-                    if bullet_z_min < enemy_z_max && bullet_z_max > enemy_z_min {
-                        util::remove_entity(*enemy_entity, &mut store, &mut window);
-                        util::remove_entity(*bullet_entity, &mut store, &mut window);
-
-                        let mut scores: Vec<EntityId> = Vec::new(); 
-                        store.collect_with(&component_filter!(Score), &mut scores);
-                        let mut score = store.get::<Score>(scores[0]).unwrap();
-                        score.total = score.total + 100; 
-                        let _ = store.set::<Score>(scores[0], score); 
-                    }
+        let mut pairs = sweep_and_prune(&enemies, &bullets);
+        pairs.sort();
+        assert_eq!(pairs, expected);
+    }
+
+    //run with `cargo test -- --nocapture` to see the timings
+    #[test]
+    fn sweep_and_prune_finds_the_same_hits_as_the_nested_loop() {
+        let mut store = recs::Ecs::new();
+        let (enemies, bullets) = scene(&mut store);
+
+        let started = Instant::now();
+        let mut expected: Vec<(usize, usize)> = Vec::new();
+        for (enemy_index, enemy) in enemies.iter().enumerate() {
+            for (bullet_index, bullet) in bullets.iter().enumerate() {
+                if swept_intersects(bullet, enemy) {
+                    expected.push((enemy_index, bullet_index));
                 }
             }
         }
+        let nested_loop = started.elapsed();
+
+        let started = Instant::now();
+        let mut hits: Vec<(usize, usize)> = sweep_and_prune(&enemies, &bullets).into_iter()
+            .filter(|(enemy_index, bullet_index)| swept_intersects(&bullets[*bullet_index], &enemies[*enemy_index]))
+            .collect();
+        let broad_phase = started.elapsed();
+        println!("{} enemies, {} bullets: nested loop {:?}, sweep and prune {:?}", enemies.len(), bullets.len(), nested_loop, broad_phase);
+
+        hits.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(hits, expected);
     }
 }
\ No newline at end of file
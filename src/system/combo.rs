@@ -0,0 +1,44 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//the combo count needed for each multiplier tier, starting at x2
+const TIERS: [i32; 3] = [10, 25, 50];
+
+pub fn multiplier(combo: &Combo) -> i32 {
+    let reached = TIERS.iter().filter(|threshold| combo.count >= **threshold).count();
+    return 1 + reached as i32;
+}
+
+fn on_beat(judgement: Option<Judgement>) -> bool {
+    return judgement == Some(Judgement::Perfect) || judgement == Some(Judgement::Great);
+}
+
+pub fn run(store: &mut recs::Ecs, events: &Vec<GameEvent>) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Combo), &mut entities);
+    let mut combo = store.get::<Combo>(entities[0]).unwrap();
+
+    for event in events.iter() {
+        match event {
+            GameEvent::ShotFired{ judgement: Judgement::Miss } => combo.count = 0,
+            GameEvent::ShotFired{ judgement: Judgement::Perfect } | GameEvent::ShotFired{ judgement: Judgement::Great } => combo.count = combo.count + 1,
+            //an on-beat shot was already counted when it was fired, a hit only counts for the other shots
+            GameEvent::EnemyDestroyed{ by: GameObjectType::Bullet, judgement, .. } if !on_beat(*judgement) => combo.count = combo.count + 1,
+            GameEvent::BossHit{ judgement, .. } if !on_beat(*judgement) => combo.count = combo.count + 1,
+            GameEvent::PlayerHit{ .. } => combo.count = 0,
+            _ => ()
+        }
+    }
+
+    if combo.count > combo.longest {
+        combo.longest = combo.count;
+    }
+
+    if combo.count == 0 {
+        combo.ui.set_text("");
+    } else {
+        combo.ui.set_text(format!("combo: {} x{}", combo.count, multiplier(&combo)));
+    }
+
+    let _ = store.set(entities[0], combo);
+}
\ No newline at end of file
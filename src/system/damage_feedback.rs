@@ -0,0 +1,39 @@
+use three::Object;
+use rand::Rng;
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+const SHAKE_DURATION: f32 = 0.3;
+const SHAKE_STRENGTH: f32 = 0.25;
+//how many times per second the player mesh is toggled while invulnerable
+const BLINK_RATE: f32 = 12.0;
+const CAMERA_POSITION: [f32; 3] = [0.0, 0.0, 10.0];
+
+pub fn run(store: &mut recs::Ecs, camera: &three::camera::Camera, rhythm: &Rhythm, feedback: &mut DamageFeedback, visual_intensity: f32, events: &Vec<GameEvent>) {
+    if events.iter().any(|event| match event { GameEvent::PlayerHit{ .. } => true, _ => false }) {
+        feedback.shake_until = rhythm.now + SHAKE_DURATION;
+        feedback.source.play(&feedback.hit_sound);
+    }
+
+    //the shake fades out towards the end, it is left out completely when the visual intensity is turned off
+    if rhythm.now < feedback.shake_until && visual_intensity > 0.0 {
+        let strength = SHAKE_STRENGTH * visual_intensity * (feedback.shake_until - rhythm.now) / SHAKE_DURATION;
+        let mut random = rand::thread_rng();
+        camera.set_position([
+            CAMERA_POSITION[0] + random.gen_range(-strength, strength),
+            CAMERA_POSITION[1] + random.gen_range(-strength, strength),
+            CAMERA_POSITION[2]
+        ]);
+    } else {
+        camera.set_position(CAMERA_POSITION);
+    }
+
+    let mut players: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Health, GameObject), &mut players);
+    for player in players.iter() {
+        let health = store.get::<Health>(*player).unwrap();
+        let gameobject = store.get::<GameObject>(*player).unwrap();
+        let invulnerable = rhythm.now < health.invulnerable_until;
+        gameobject.mesh.set_visible(!invulnerable || (rhythm.now * BLINK_RATE) as i32 % 2 == 0);
+    }
+}
\ No newline at end of file
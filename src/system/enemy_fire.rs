@@ -0,0 +1,40 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//enemies only shoot once they are close enough to be seen clearly and while they are still in front of the player
+const FIRING_RANGE: (f32, f32) = (-25.0, -3.0);
+
+//enemies that shoot all fire on the same beats so their volleys land with the music
+pub fn run(window: &mut three::Window, store: &mut recs::Ecs, rhythm: &Rhythm, events: &Vec<GameEvent>) {
+    if !events.iter().any(|event| *event == GameEvent::Beat) {
+        return;
+    }
+
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Enemy, Position), &mut entities);
+
+    let mut players: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Health, Position), &mut players);
+    if players.is_empty() {
+        return;
+    }
+    let player = store.get::<Position>(players[0]).unwrap();
+
+    for entity in entities.iter() {
+        let enemy = store.get::<Enemy>(*entity).unwrap();
+        let position = store.get::<Position>(*entity).unwrap();
+
+        let shoots_every = match enemy.archetype.shoots_every {
+            Some(beats) => beats,
+            None => continue
+        };
+
+        if rhythm.beats % shoots_every != 0 || position.z < FIRING_RANGE.0 || position.z > FIRING_RANGE.1 {
+            continue;
+        }
+
+        let delta = [player.x - position.x, player.y - position.y, player.z - position.z];
+        let length = (delta[0] * delta[0] + delta[1] * delta[1] + delta[2] * delta[2]).sqrt();
+        factory::create_hostile_bullet(window, store, position, [delta[0] / length, delta[1] / length, delta[2] / length]);
+    }
+}
\ No newline at end of file
@@ -3,16 +3,21 @@ use rand::Rng;
 use crate::*; 
 
 //replace this for only running on peak detection (match peak_deteciion) (match in match!!)
-pub fn run(window: &mut three::Window, store: &mut Ecs, enemy_scheduler: &mut clokwerk::Scheduler, receiver: &Receiver<Vec<Position>>) {
+pub fn run(window: &mut three::Window, store: &mut Ecs, enemy_scheduler: &mut clokwerk::Scheduler, receiver: &Receiver<formation::FormationParameters>, onset_strength: f64, spectral_centroid: f64, intensity: f32) {
     enemy_scheduler.run_pending();
     match receiver.try_recv() {
-        Ok(pending_enemies) => {
+        Ok(mut parameters) => {
+            //the formation size is rolled on the scheduler thread, it is scaled here where the store and the audio are available
+            parameters.count = ((parameters.count as f32) * difficulty::current(store).formation_size * intensity).round() as i32;
+            let shape = formation::select(onset_strength, spectral_centroid);
+            let pending_enemies = formation::build(shape, &parameters);
             if pending_enemies.is_empty() {
                 return
             }
 
-            for position in pending_enemies.iter().rev() {
-                factory::create_enemy(window, store, *position); 
+            let archetype = archetype::from_onset_strength(onset_strength);
+            for (position, movement) in pending_enemies.iter().rev() {
+                factory::create_enemy(window, store, *position, *movement, archetype); 
                 // create_single(window, store);
             }   
         },
@@ -27,26 +32,45 @@ pub fn create_single(window: &mut three::Window, store: &mut Ecs) {
         x: random.gen_range(-5.0, 5.0),
         y: random.gen_range(-5.0, 5.0),
         z: random.gen_range(-30.0, -25.0)
-    });
+    }, Movement::Straight, &archetype::DRONE);
 }
 
-pub fn schedule_callback() -> Vec<Position> { 
+//enemies that break up when they are shot leave their smaller pieces scattered around where they died
+pub fn split(window: &mut three::Window, store: &mut Ecs, events: &Vec<GameEvent>) {
     let mut random = rand::thread_rng();
-    let num_meteors: i32 = random.gen_range(5, 15);
-    let radius: f32 = random.gen_range(2.0, 5.0);
-    let d_angle = 360.0 / (num_meteors as f32); 
-    let z = random.gen_range(-30.0, -25.0);
-
-    let mut pending_enemies: Vec<Position> = Vec::new();
-    for index in 0..num_meteors {
-        let cartesian_coordinates = util::polar_to_cartesian(radius, d_angle * ((index as f32) + 1.0));
- 
-        pending_enemies.push(Position{ 
-                x: cartesian_coordinates[0],
-                y: cartesian_coordinates[1],
-                z: z
-            });
+
+    for event in events.iter() {
+        match event {
+            GameEvent::EnemyDestroyed{ by: GameObjectType::Bullet, archetype, position, .. } => {
+                let (piece, count) = match archetype.splits_into {
+                    Some(split) => split,
+                    None => continue
+                };
+
+                let d_angle = 360.0 / (count as f32);
+                let offset: f32 = random.gen_range(0.0, d_angle);
+                for index in 0..count {
+                    let cartesian_coordinates = util::polar_to_cartesian(archetype.size, offset + d_angle * (index as f32));
+                    factory::create_enemy(window, store, Position{
+                        x: position.x + cartesian_coordinates[0],
+                        y: position.y + cartesian_coordinates[1],
+                        z: position.z
+                    }, Movement::Homing{ turn_rate: 0.02 }, piece);
+                }
+            },
+            _ => ()
+        }
     }
+}
+
+//only the random parameters are rolled here, the shape is picked when the enemies spawn because it depends on the audio
+pub fn schedule_callback() -> formation::FormationParameters { 
+    let mut random = rand::thread_rng();
 
-    return pending_enemies;
+    return formation::FormationParameters {
+        count: random.gen_range(5, 15),
+        radius: random.gen_range(2.0, 5.0),
+        z: random.gen_range(-30.0, -25.0),
+        rotation: random.gen_range(0.0, 360.0)
+    };
 }
\ No newline at end of file
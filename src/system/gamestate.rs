@@ -1,8 +1,445 @@
-#[allow(unused_imports)]
-use recs::{EntityId, component_filter};
 use crate::*; 
+use crate::action::{Action, Controls};
 
-#[allow(dead_code, unused_variables)]
-pub fn run(window: &mut three::Window, store: &mut recs::Ecs) {
-    //TODO: switch screens
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TitleOption {
+    Play,
+    TrackSelect,
+    Difficulty,
+    HighScores,
+    Settings,
+    Quit
+}
+
+const TITLE_OPTIONS: [TitleOption; 6] = [
+    TitleOption::Play,
+    TitleOption::TrackSelect,
+    TitleOption::Difficulty,
+    TitleOption::HighScores,
+    TitleOption::Settings,
+    TitleOption::Quit
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PauseOption {
+    Resume,
+    Restart,
+    Settings,
+    Quit
+}
+
+const PAUSE_OPTIONS: [PauseOption; 4] = [
+    PauseOption::Resume,
+    PauseOption::Restart,
+    PauseOption::Settings,
+    PauseOption::Quit
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SettingsOption {
+    Volume,
+    InputDevice,
+    Latency,
+    Difficulty,
+    Fullscreen,
+    VisualIntensity,
+    KeyBindings,
+    Back
+}
+
+const SETTINGS_OPTIONS: [SettingsOption; 8] = [
+    SettingsOption::Volume,
+    SettingsOption::InputDevice,
+    SettingsOption::Latency,
+    SettingsOption::Difficulty,
+    SettingsOption::Fullscreen,
+    SettingsOption::VisualIntensity,
+    SettingsOption::KeyBindings,
+    SettingsOption::Back
+];
+
+//how many entries of a long list fit on the screen at once
+const VISIBLE_ENTRIES: usize = 8;
+const VISIBLE_HIGH_SCORES: usize = 10;
+
+fn title_index(option: TitleOption) -> usize {
+    return TITLE_OPTIONS.iter().position(|candidate| *candidate == option).unwrap_or(0);
+}
+
+fn pause_index(option: PauseOption) -> usize {
+    return PAUSE_OPTIONS.iter().position(|candidate| *candidate == option).unwrap_or(0);
+}
+
+fn settings_index(option: SettingsOption) -> usize {
+    return SETTINGS_OPTIONS.iter().position(|candidate| *candidate == option).unwrap_or(0);
+}
+
+//moving up and down moves the selection, it wraps around at both ends
+fn navigate(controls: &Controls, selected: &mut usize, count: usize) {
+    if count == 0 {
+        return;
+    }
+
+    if controls.pressed(Action::MoveUp) {
+        *selected = (*selected + count - 1) % count;
+    }
+    if controls.pressed(Action::MoveDown) {
+        *selected = (*selected + 1) % count;
+    }
+}
+
+//moving left and right steps through the values of a setting, confirming steps forward as well
+fn step(controls: &Controls) -> i32 {
+    if controls.pressed(Action::MoveLeft) {
+        return -1;
+    }
+    if controls.pressed(Action::MoveRight) || controls.pressed(Action::Confirm) {
+        return 1;
+    }
+    return 0;
+}
+
+//values are rounded to the step size so repeated steps don't drift
+fn step_value(value: f32, step: i32, size: f32, min: f32, max: f32) -> f32 {
+    let stepped = value + size * (step as f32);
+    return ((stepped / size).round() * size).max(min).min(max);
+}
+
+fn cycle<T: Clone + PartialEq>(options: &[T], current: &T, step: i32) -> T {
+    let count = options.len() as i32;
+    let index = options.iter().position(|option| option == current).unwrap_or(0) as i32;
+    return options[((index + step) % count + count) as usize % options.len()].clone();
+}
+
+fn highlight(label: String, selected: bool) -> String {
+    if selected {
+        return format!("> {} <", label);
+    }
+    return label;
+}
+
+fn track_name(session: &Session) -> String {
+    match session.track {
+        Some(index) => return session.tracks[index].name.clone(),
+        None => return "microphone".to_string()
+    }
+}
+
+fn title_text(session: &Session, selected: usize) -> String {
+    let options: Vec<String> = TITLE_OPTIONS.iter().enumerate().map(|(index, option)| {
+        let label = match option {
+            TitleOption::Play => "play".to_string(),
+            TitleOption::TrackSelect => format!("track: {}", track_name(session)),
+            TitleOption::Difficulty => format!("difficulty: {}", session.settings.difficulty.name),
+            TitleOption::HighScores => "high scores".to_string(),
+            TitleOption::Settings => "settings".to_string(),
+            TitleOption::Quit => "quit".to_string()
+        };
+        return highlight(label, index == selected);
+    }).collect();
+
+    return format!("INSYNC\n\n{}", options.join("\n"));
+}
+
+//long lists scroll to keep the selection in view
+fn visible_entries(entries: Vec<String>, selected: usize) -> String {
+    let first = selected.saturating_sub(VISIBLE_ENTRIES / 2).min(entries.len().saturating_sub(VISIBLE_ENTRIES));
+    let lines: Vec<String> = entries.into_iter().enumerate().skip(first).take(VISIBLE_ENTRIES).map(|(index, entry)| {
+        return highlight(entry, index == selected);
+    }).collect();
+
+    return lines.join("\n");
+}
+
+//the microphone is always the first entry
+fn track_text(session: &Session, selected: usize) -> String {
+    let mut entries: Vec<String> = vec!["microphone".to_string()];
+    entries.extend(session.tracks.iter().map(|track| track::describe(track)));
+
+    return format!("tracks in {}\n\n{}", session.track_directory, visible_entries(entries, selected));
+}
+
+//the last entry leads back to the settings
+fn bindings_text(session: &Session, selected: usize, listening: bool) -> String {
+    let mut entries: Vec<String> = session.settings.bindings.iter().map(|binding| {
+        let keys: Vec<String> = binding.keys.iter().map(|key| action::key_name(*key)).collect();
+        return format!("{}: {}", action::name(binding.action).replace("_", " "), keys.join(", "));
+    }).collect();
+    entries.push("back".to_string());
+
+    let hint = if listening { "press a key to add or remove it, keys move over from other actions, pause cancels" } else { "confirm to change a binding" };
+    return format!("key bindings\n{}\n\n{}", hint, visible_entries(entries, selected));
+}
+
+//pressing a key that is already bound removes it, an action always keeps at least one key
+//a key can only do one thing, it is taken away from the action it was bound to unless that was the action's last key
+fn toggle_key(bindings: &mut Vec<action::Binding>, selected: usize, key: three::Key) {
+    match bindings[selected].keys.iter().position(|bound| *bound == key) {
+        Some(index) if bindings[selected].keys.len() > 1 => {
+            bindings[selected].keys.remove(index);
+            return;
+        },
+        Some(_) => return,
+        None => ()
+    }
+
+    let taken = (0..bindings.len()).find(|index| *index != selected && bindings[*index].keys.contains(&key));
+    if let Some(other) = taken {
+        if bindings[other].keys.len() == 1 {
+            return;
+        }
+        bindings[other].keys.retain(|bound| *bound != key);
+    }
+
+    bindings[selected].keys.push(key);
+}
+
+fn high_score_text() -> String {
+    let scores = highscore::load();
+    if scores.is_empty() {
+        return "high scores\n\nnothing recorded yet".to_string();
+    }
+
+    let lines: Vec<String> = scores.iter().take(VISIBLE_HIGH_SCORES).enumerate().map(|(index, (difficulty, score))| {
+        return format!("{}. {} ({})", index + 1, score, difficulty);
+    }).collect();
+
+    return format!("high scores\n\n{}", lines.join("\n"));
+}
+
+fn pause_text(selected: usize) -> String {
+    let options: Vec<String> = PAUSE_OPTIONS.iter().enumerate().map(|(index, option)| {
+        let label = match option {
+            PauseOption::Resume => "resume",
+            PauseOption::Restart => "restart",
+            PauseOption::Settings => "settings",
+            PauseOption::Quit => "quit"
+        };
+        return highlight(label.to_string(), index == selected);
+    }).collect();
+
+    return format!("paused\n\n{}", options.join("\n"));
+}
+
+fn settings_text(session: &Session, selected: usize) -> String {
+    let settings = &session.settings;
+    let options: Vec<String> = SETTINGS_OPTIONS.iter().enumerate().map(|(index, option)| {
+        let label = match option {
+            SettingsOption::Volume => format!("volume: {:.0}%", settings.volume * 100.0),
+            SettingsOption::InputDevice => format!("microphone: {}", settings.input_device.clone().unwrap_or("default".to_string())),
+            SettingsOption::Latency => format!("latency: {:.0} ms", settings.latency * 1000.0),
+            SettingsOption::Difficulty => format!("difficulty: {}", settings.difficulty.name),
+            SettingsOption::Fullscreen => format!("fullscreen: {}", if settings.fullscreen { "on" } else { "off" }),
+            SettingsOption::VisualIntensity => format!("visual intensity: {:.0}%", settings.visual_intensity * 100.0),
+            SettingsOption::KeyBindings => "key bindings".to_string(),
+            SettingsOption::Back => "back".to_string()
+        };
+        return highlight(label, index == selected);
+    }).collect();
+
+    return format!("settings\n\n{}", options.join("\n"));
+}
+
+//returns whether the setting actually changed
+fn change_setting(session: &mut Session, option: SettingsOption, step: i32) -> bool {
+    let before = session.settings.clone();
+    let settings = &mut session.settings;
+
+    match option {
+        SettingsOption::Volume => settings.volume = step_value(settings.volume, step, 0.1, 0.0, 1.0),
+        SettingsOption::InputDevice => {
+            //none stands for the default device and comes first
+            let mut devices: Vec<Option<String>> = vec![None];
+            devices.extend(session.input_devices.iter().map(|device| Some(device.clone())));
+            settings.input_device = cycle(&devices, &settings.input_device, step);
+        },
+        SettingsOption::Latency => settings.latency = step_value(settings.latency, step, 0.01, 0.0, 0.5),
+        SettingsOption::Difficulty => settings.difficulty = cycle(&difficulty::PRESETS, &settings.difficulty, step),
+        SettingsOption::Fullscreen => settings.fullscreen = !settings.fullscreen,
+        SettingsOption::VisualIntensity => settings.visual_intensity = step_value(settings.visual_intensity, step, 0.25, 0.0, 1.0),
+        SettingsOption::KeyBindings | SettingsOption::Back => ()
+    }
+
+    return *settings != before;
+}
+
+//the menu screens are driven by the keyboard, enter confirms and escape goes back
+//only actions that change the game itself are handed back, moving between screens happens in here
+pub fn run(window: &mut three::Window, controls: &Controls, state: &mut GameState, session: &mut Session, menu: &mut three::Text, now: f32) -> Option<MenuAction> {
+    let mut action: Option<MenuAction> = None;
+
+    match state {
+        GameState::Title{ selected } => {
+            navigate(controls, selected, TITLE_OPTIONS.len());
+            if controls.pressed(Action::Confirm) {
+                match TITLE_OPTIONS[*selected] {
+                    TitleOption::Play => action = Some(MenuAction::Play),
+                    TitleOption::TrackSelect => *state = GameState::TrackSelect{ selected: session.track.map(|index| index + 1).unwrap_or(0) },
+                    TitleOption::Difficulty => {
+                        session.settings.difficulty = cycle(&difficulty::PRESETS, &session.settings.difficulty, 1);
+                        action = Some(MenuAction::ApplySettings);
+                    },
+                    //the scores only change between games, so the file is read once when the screen opens
+                    TitleOption::HighScores => {
+                        *state = GameState::HighScores;
+                        menu.set_text(high_score_text());
+                    },
+                    TitleOption::Settings => *state = GameState::Settings{ selected: 0, paused_since: None },
+                    TitleOption::Quit => action = Some(MenuAction::Quit)
+                }
+            }
+        },
+        GameState::Settings{ selected, paused_since } => {
+            navigate(controls, selected, SETTINGS_OPTIONS.len());
+            let option = SETTINGS_OPTIONS[*selected];
+            let leave = controls.pressed(Action::Pause) || (option == SettingsOption::Back && controls.pressed(Action::Confirm));
+
+            let direction = step(controls);
+            if direction != 0 && change_setting(session, option, direction) {
+                action = Some(MenuAction::ApplySettings);
+            }
+
+            if leave {
+                action = Some(MenuAction::SaveSettings);
+                *state = match paused_since {
+                    Some(since) => GameState::Paused{ since: *since, selected: pause_index(PauseOption::Settings) },
+                    None => GameState::Title{ selected: title_index(TitleOption::Settings) }
+                };
+            } else if option == SettingsOption::KeyBindings && controls.pressed(Action::Confirm) {
+                *state = GameState::Bindings{ selected: 0, listening: false, paused_since: *paused_since };
+            }
+        },
+        GameState::Bindings{ selected, listening, paused_since } => {
+            let count = session.settings.bindings.len() + 1;
+            if *listening {
+                if controls.pressed(Action::Pause) {
+                    *listening = false;
+                } else if let Some(key) = action::pressed_key(window) {
+                    toggle_key(&mut session.settings.bindings, *selected, key);
+                    *listening = false;
+                    action = Some(MenuAction::ApplySettings);
+                }
+            } else {
+                navigate(controls, selected, count);
+                let back = *selected == count - 1;
+                if controls.pressed(Action::Pause) || (back && controls.pressed(Action::Confirm)) {
+                    *state = GameState::Settings{ selected: settings_index(SettingsOption::KeyBindings), paused_since: *paused_since };
+                } else if controls.pressed(Action::Confirm) {
+                    *listening = true;
+                }
+            }
+        },
+        GameState::TrackSelect{ selected } => {
+            navigate(controls, selected, session.tracks.len() + 1);
+            if controls.pressed(Action::Confirm) {
+                session.track = if *selected == 0 { None } else { Some(*selected - 1) };
+                session.audio = match session.track {
+                    Some(index) => track::load(&mut session.tracks[index]),
+                    None => None
+                };
+                *state = GameState::Title{ selected: title_index(TitleOption::TrackSelect) };
+            } else if controls.pressed(Action::Pause) {
+                *state = GameState::Title{ selected: title_index(TitleOption::TrackSelect) };
+            }
+        },
+        GameState::HighScores => {
+            if controls.pressed(Action::Confirm) || controls.pressed(Action::Pause) {
+                *state = GameState::Title{ selected: title_index(TitleOption::HighScores) };
+            }
+        },
+        GameState::Results => {
+            if controls.pressed(Action::Confirm) || controls.pressed(Action::Pause) {
+                *state = GameState::Title{ selected: title_index(TitleOption::Play) };
+                action = Some(MenuAction::BackToTitle);
+            }
+        },
+        GameState::Playing => {
+            if controls.pressed(Action::Pause) {
+                *state = GameState::Paused{ since: now, selected: 0 };
+                window.scene.add(&*menu);
+            }
+        },
+        GameState::Paused{ since, selected } => {
+            let since = *since;
+            navigate(controls, selected, PAUSE_OPTIONS.len());
+            if controls.pressed(Action::Pause) {
+                action = Some(MenuAction::Resume);
+            } else if controls.pressed(Action::Confirm) {
+                match PAUSE_OPTIONS[*selected] {
+                    PauseOption::Resume => action = Some(MenuAction::Resume),
+                    PauseOption::Restart => action = Some(MenuAction::Restart),
+                    PauseOption::Settings => *state = GameState::Settings{ selected: 0, paused_since: Some(since) },
+                    PauseOption::Quit => action = Some(MenuAction::Quit)
+                }
+            }
+        }
+    }
+
+    match state {
+        GameState::Title{ selected } => menu.set_text(title_text(session, *selected)),
+        GameState::TrackSelect{ selected } => menu.set_text(track_text(session, *selected)),
+        GameState::Settings{ selected, .. } => menu.set_text(settings_text(session, *selected)),
+        GameState::Bindings{ selected, listening, .. } => menu.set_text(bindings_text(session, *selected, *listening)),
+        GameState::Paused{ selected, .. } => menu.set_text(pause_text(*selected)),
+        GameState::HighScores | GameState::Results | GameState::Playing => ()
+    }
+
+    return action;
+}
+
+//hides the menu and returns how long the game was paused so the clock can skip over it
+pub fn resume(window: &mut three::Window, state: &mut GameState, menu: &three::Text, now: f32) -> f32 {
+    let paused_for = match state {
+        GameState::Paused{ since, .. } => now - *since,
+        _ => 0.0
+    };
+
+    window.scene.remove(menu);
+    *state = GameState::Playing;
+    return paused_for;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use three::Key;
+    use super::*;
+
+    fn index(bindings: &Vec<action::Binding>, action: Action) -> usize {
+        return bindings.iter().position(|binding| binding.action == action).unwrap();
+    }
+
+    #[test]
+    fn toggle_key_moves_a_key_from_another_action() {
+        let mut bindings = action::default_bindings();
+        let move_down = index(&bindings, Action::MoveDown);
+        let move_up = index(&bindings, Action::MoveUp);
+
+        toggle_key(&mut bindings, move_down, Key::W);
+        assert_eq!(bindings[move_down].keys, vec![Key::S, Key::Down, Key::W]);
+        assert_eq!(bindings[move_up].keys, vec![Key::Up]);
+    }
+
+    #[test]
+    fn toggle_key_keeps_the_last_key_of_another_action() {
+        let mut bindings = action::default_bindings();
+        let move_down = index(&bindings, Action::MoveDown);
+        let confirm = index(&bindings, Action::Confirm);
+
+        toggle_key(&mut bindings, move_down, Key::Return);
+        assert_eq!(bindings[move_down].keys, vec![Key::S, Key::Down]);
+        assert_eq!(bindings[confirm].keys, vec![Key::Return]);
+    }
+
+    #[test]
+    fn toggle_key_removes_a_bound_key_but_never_the_last() {
+        let mut bindings = action::default_bindings();
+        let move_up = index(&bindings, Action::MoveUp);
+        let fire = index(&bindings, Action::Fire);
+
+        toggle_key(&mut bindings, move_up, Key::W);
+        assert_eq!(bindings[move_up].keys, vec![Key::Up]);
+        toggle_key(&mut bindings, fire, Key::Space);
+        assert_eq!(bindings[fire].keys, vec![Key::Space]);
+    }
 }
\ No newline at end of file
@@ -1,36 +1,45 @@
 use recs::{EntityId, component_filter};
 use crate::*; 
 
-pub fn run(mut window: &mut three::Window, mut store: &mut Ecs) {
+pub fn run(mut window: &mut three::Window, mut store: &mut Ecs, rhythm: &Rhythm, events: &mut Vec<GameEvent>, commands: &mut CommandBuffer) {
     let mut entities: Vec<EntityId> = Vec::new();
-    let mut player: Vec<EntityId> = Vec::new();
     store.collect_with(&component_filter!(GameObject, Position), &mut entities);
-    store.collect_with(&component_filter!(Health), &mut player);
-    entities.push(player[0]);
 
     for entity in entities.iter().rev() {
         let gameobject = store.get::<GameObject>(*entity).unwrap();
         let position = store.get::<Position>(*entity).unwrap();
         match gameobject.object_type {
-            GameObjectType::Enemy => {
+            GameObjectType::Enemy | GameObjectType::Pickup | GameObjectType::HostileBullet => {
                 //if traveled beyond camera
                 if position.z > 12.0 {
-                    util::remove_entity(*entity, &mut store, &mut window);
+                    util::despawn(*entity, commands);
                 }
             },
             GameObjectType::Bullet => {
-                //if traveled beyond the edge of the world
-                if position.z < -35.0 {
-                    util::remove_entity(*entity, &mut store, &mut window);
-                }
-            },
-            GameObjectType::Player => {
-                let health = store.get::<Health>(*entity).unwrap();
+                let projectile = store.get::<Projectile>(*entity).ok();
+                //beams only last a moment
+                let expired = match projectile.as_ref().and_then(|projectile| projectile.expires) {
+                    Some(expires) => expires <= rhythm.now,
+                    None => false
+                };
 
-                if health.total == 0 {
-                    util::remove_entity(*entity, store, window);
+                //if traveled beyond the edge of the world, bullets that were used up on a hit this frame are already gone
+                if (position.z < -35.0 || expired) && !util::is_despawning(*entity, commands) {
+                    util::despawn(*entity, commands);
+                    if projectile.map(|projectile| projectile.hits.is_empty()).unwrap_or(true) {
+                        events.push(GameEvent::ProjectileMissed);
+                    }
                 }
-            }, 
+            },
+            //a player without lives stays until the game over screen tears down the whole store
+            GameObjectType::Player => (),
+            //bosses clean up their own parts
+            GameObjectType::BossPart => (),
         }
     }
+
+    //the single place where entities are actually removed from the store and the scene
+    for entity in commands.despawn.drain(..) {
+        util::remove_entity(entity, &mut store, &mut window);
+    }
 }
\ No newline at end of file
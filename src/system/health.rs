@@ -1,13 +1,28 @@
 use recs::{EntityId, component_filter};
 use crate::*; 
 
-pub fn run(store: &mut recs::Ecs) {
+pub fn run(store: &mut recs::Ecs, events: &Vec<GameEvent>) {
     let mut enitites: Vec<EntityId> = Vec::new(); 
     store.collect_with(&component_filter!(Health), &mut enitites);
     let mut health = store.get::<Health>(enitites[0]).unwrap();
+
+    for event in events.iter() {
+        match event {
+            GameEvent::PlayerHit{ .. } => health.total = health.total - 1,
+            GameEvent::PickupCollected{ kind: PickupKind::ExtraLife } => health.total = health.total + 1,
+            _ => ()
+        }
+    }
     
     let health_prefix: &str = "lives: ";
     let health_string: &str = &health.total.to_string();
 
    health.ui.set_text(format!("{}{}", health_prefix, health_string));
+   let _ = store.set(enitites[0], health);
+}
+
+pub fn is_game_over(store: &recs::Ecs) -> bool {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Health), &mut entities);
+    return !entities.is_empty() && store.get::<Health>(entities[0]).unwrap().total <= 0;
 }
\ No newline at end of file
@@ -1,7 +1,30 @@
 use recs::{EntityId, component_filter};
 use crate::*; 
+use crate::action::Action;
 
-pub fn run(mut window: &mut three::Window, mut store: &mut Ecs) {
+//the furthest the center of the player can get from the tunnel axis without any of its corners going through the wall
+fn max_radius(store: &recs::Ecs, gameobject: &GameObject) -> f32 {
+    let mut tunnels: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(Tunnel), &mut tunnels);
+    let tunnel = store.get::<Tunnel>(tunnels[0]).unwrap();
+
+    let extent = gameobject.vertices.iter()
+        .map(|vertex| (vertex.x * vertex.x + vertex.y * vertex.y).sqrt())
+        .fold(0.0, |max: f32, distance| max.max(distance));
+
+    return tunnel.radius - extent;
+}
+
+fn clamp_to_tunnel(position: &mut Position, max_radius: f32) {
+    let radius = (position.x * position.x + position.y * position.y).sqrt();
+
+    if radius > max_radius {
+        position.x = position.x * (max_radius / radius);
+        position.y = position.y * (max_radius / radius);
+    }
+}
+
+pub fn run(store: &mut Ecs, controls: &action::Controls) {
     let component_filter = component_filter!(Position, GameObject);
     let mut entities: Vec<EntityId> = Vec::new(); 
     store.collect_with(&component_filter, &mut entities);
@@ -11,29 +34,37 @@ pub fn run(mut window: &mut three::Window, mut store: &mut Ecs) {
         if gameobject.object_type == GameObjectType::Player {
             let position = store.get::<Position>(entity).unwrap();
 
-            let space_button = three::Button::from(three::controls::Button::Key(three::controls::Key::Space));
-            if window.input.hit(three::Key::Space) && window.input.hit_count(space_button) == 1 {
-                factory::create_bullet(&mut window, &mut store, position); 
-            }; 
+            let mut steering = store.get::<Steering>(entity).unwrap_or(Steering::Free);
+            if controls.pressed(Action::ToggleOrbit) {
+                steering = match steering {
+                    Steering::Free => Steering::Orbit{ angle: position.y.atan2(position.x).to_degrees() },
+                    Steering::Orbit{ .. } => Steering::Free
+                };
+            }
 
             let mut new_position = position.clone(); 
+            let max_radius = max_radius(&store, &gameobject);
 
-            if window.input.hit(three::Key::W) {
-                new_position.y = new_position.y + gameobject.velocity; 
-            }
+            match steering {
+                Steering::Free => {
+                    new_position.x = new_position.x + gameobject.velocity * controls.movement[0];
+                    new_position.y = new_position.y + gameobject.velocity * controls.movement[1];
 
-            if window.input.hit(three::Key::S) {
-                new_position.y = new_position.y - gameobject.velocity; 
-            }
+                    clamp_to_tunnel(&mut new_position, max_radius);
+                },
+                Steering::Orbit{ angle } => {
+                    //left and right run along the wall, the player covers twice its free speed in arc length
+                    let d_angle = (gameobject.velocity * 2.0 / max_radius).to_degrees();
+                    let new_angle = angle - d_angle * controls.movement[0];
 
-            if window.input.hit(three::Key::A) {
-                new_position.x = new_position.x - gameobject.velocity; 
-            }
-        
-            if window.input.hit(three::Key::D) {
-                new_position.x = new_position.x + gameobject.velocity;  
+                    let cartesian_coordinates = util::polar_to_cartesian(max_radius, new_angle);
+                    new_position.x = cartesian_coordinates[0];
+                    new_position.y = cartesian_coordinates[1];
+                    steering = Steering::Orbit{ angle: new_angle % 360.0 };
+                }
             }
 
+            let _ = store.set(entity, steering);
             let _ = store.set::<Position>(entity, new_position).unwrap();       
         }
     }
@@ -1,9 +1,17 @@
 pub mod audio_analysis;
+pub mod boss;
 pub mod collision;
+pub mod combo;
+pub mod damage_feedback;
+pub mod enemy_fire;
 pub mod enemy_spawn;
 pub mod gamestate; 
 pub mod garbage_collection; 
 pub mod health; 
 pub mod input;
+pub mod pickup;
 pub mod position;
+pub mod rhythm;
 pub mod score;
+pub mod statistics;
+pub mod weapon;
@@ -0,0 +1,83 @@
+use rand::Rng;
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//only strong onsets can drop a pickup and even then only some of the time
+const SPAWN_STRENGTH: f64 = 120.0;
+const SPAWN_CHANCE: f64 = 0.2;
+//how long timed power-ups last (seconds)
+const RAPID_FIRE_DURATION: f32 = 8.0;
+const SPREAD_SHOT_DURATION: f32 = 8.0;
+const SLOW_MOTION_DURATION: f32 = 5.0;
+//how much slower everything but the player moves during slow motion
+pub const SLOW_MOTION_SCALE: f32 = 0.5;
+
+pub fn spawn(window: &mut three::Window, store: &mut recs::Ecs, onset_strength: f64) {
+    let mut random = rand::thread_rng();
+    if onset_strength < SPAWN_STRENGTH || random.gen::<f64>() > SPAWN_CHANCE {
+        return;
+    }
+
+    let kind = match random.gen_range(0, 5) {
+        0 => PickupKind::ExtraLife,
+        1 => PickupKind::RapidFire,
+        2 => PickupKind::SpreadShot,
+        3 => PickupKind::Shield,
+        _ => PickupKind::SlowMotion
+    };
+
+    let cartesian_coordinates = util::polar_to_cartesian(random.gen_range(0.0, 4.0), random.gen_range(0.0, 360.0));
+    factory::create_pickup(window, store, Position{
+        x: cartesian_coordinates[0],
+        y: cartesian_coordinates[1],
+        z: random.gen_range(-30.0, -25.0)
+    }, kind);
+}
+
+//scale applied to the speed of everything that isn't the player
+pub fn time_scale(store: &recs::Ecs, rhythm: &Rhythm) -> f32 {
+    let mut players: Vec<EntityId> = Vec::new();
+    store.collect_with(&component_filter!(PowerUps), &mut players);
+
+    if players.is_empty() || store.get::<PowerUps>(players[0]).unwrap().slow_motion_until < rhythm.now {
+        return 1.0;
+    }
+
+    return SLOW_MOTION_SCALE;
+}
+
+pub fn run(store: &mut recs::Ecs, rhythm: &Rhythm, events: &Vec<GameEvent>) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(PowerUps), &mut entities);
+    let mut powerups = store.get::<PowerUps>(entities[0]).unwrap();
+
+    for event in events.iter() {
+        match event {
+            GameEvent::PickupCollected{ kind: PickupKind::RapidFire } => powerups.rapid_fire_until = rhythm.now + RAPID_FIRE_DURATION,
+            GameEvent::PickupCollected{ kind: PickupKind::SpreadShot } => powerups.spread_shot_until = rhythm.now + SPREAD_SHOT_DURATION,
+            GameEvent::PickupCollected{ kind: PickupKind::SlowMotion } => powerups.slow_motion_until = rhythm.now + SLOW_MOTION_DURATION,
+            GameEvent::PickupCollected{ kind: PickupKind::Shield } => powerups.shield = true,
+            GameEvent::ShieldBroken => powerups.shield = false,
+            _ => ()
+        }
+    }
+
+    let mut active: Vec<String> = Vec::new();
+    if powerups.shield {
+        active.push(String::from("shield"));
+    }
+
+    let timed = [
+        ("rapid fire", powerups.rapid_fire_until),
+        ("spread shot", powerups.spread_shot_until),
+        ("slow motion", powerups.slow_motion_until)
+    ];
+    for (name, until) in timed.iter() {
+        if *until > rhythm.now {
+            active.push(format!("{} {:.0}s", name, (until - rhythm.now).ceil()));
+        }
+    }
+
+    powerups.ui.set_text(active.join("  "));
+    let _ = store.set(entities[0], powerups);
+}
\ No newline at end of file
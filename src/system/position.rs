@@ -3,20 +3,70 @@ use three::Object;
 use recs::{EntityId, component_filter};
 use crate::*; 
 
-fn position_bullet(entity: &EntityId, store: &mut recs::Ecs) {
+fn position_bullet(entity: &EntityId, store: &mut recs::Ecs, time_scale: f32) {
     let gameobject = store.get::<GameObject>(*entity).unwrap();
     let old_position = store.get::<Position>(*entity).unwrap();
-    let new_position = Position{ x: old_position.x, y: old_position.y, z: old_position.z - gameobject.velocity};
+    let direction = store.get::<Projectile>(*entity).map(|projectile| projectile.direction).unwrap_or([0.0, 0.0, -1.0]);
+    let displacement = Displacement{
+        x: direction[0] * gameobject.velocity * time_scale,
+        y: direction[1] * gameobject.velocity * time_scale,
+        z: direction[2] * gameobject.velocity * time_scale
+    };
+    let new_position = Position{ x: old_position.x + displacement.x, y: old_position.y + displacement.y, z: old_position.z + displacement.z };
     let _ = store.set::<Position>(*entity, new_position).unwrap();
+    let _ = store.set(*entity, displacement);
 
     gameobject.mesh.set_position([new_position.x, new_position.y, new_position.z]);
 }
 
-fn position_enemy(entity: &EntityId, store: &mut recs::Ecs) {
+fn position_enemy(entity: &EntityId, store: &mut recs::Ecs, rhythm: &Rhythm, on_beat: bool, player: &Position, time_scale: f32) {
     let gameobject = store.get::<GameObject>(*entity).unwrap();
     let old_position = store.get::<Position>(*entity).unwrap();
-    let new_position = Position{ x: old_position.x, y: old_position.y, z: old_position.z + gameobject.velocity };
+    let movement = store.get::<Movement>(*entity).unwrap_or(Movement::Straight);
+    let velocity = gameobject.velocity * time_scale;
+    let mut new_position = Position{ x: old_position.x, y: old_position.y, z: old_position.z + velocity };
+
+    match movement {
+        Movement::Straight => (),
+        Movement::Spiral{ angular_velocity, radius, pulse } => {
+            let angle = old_position.y.atan2(old_position.x).to_degrees() + angular_velocity * time_scale;
+            //the swell fades out quickly after the beat so the ring looks like it's breathing
+            let swell = pulse * (-(rhythm.now - rhythm.last_beat).max(0.0) * 8.0).exp();
+            let cartesian_coordinates = util::polar_to_cartesian(radius + swell, angle);
+            new_position.x = cartesian_coordinates[0];
+            new_position.y = cartesian_coordinates[1];
+        },
+        Movement::Weave{ amplitude, frequency } => {
+            new_position.x = new_position.x + amplitude * ((new_position.z * frequency).sin() - (old_position.z * frequency).sin());
+        },
+        Movement::Homing{ turn_rate } => {
+            new_position.x = new_position.x + (player.x - old_position.x) * turn_rate * time_scale;
+            new_position.y = new_position.y + (player.y - old_position.y) * turn_rate * time_scale;
+        },
+        Movement::Hop{ distance } => {
+            new_position.z = old_position.z + velocity * 0.2;
+            if on_beat {
+                new_position.z = new_position.z + distance;
+            }
+        }
+    }
+
     let _ = store.set::<Position>(*entity, new_position).unwrap();
+    let _ = store.set(*entity, Displacement{
+        x: new_position.x - old_position.x,
+        y: new_position.y - old_position.y,
+        z: new_position.z - old_position.z
+    });
+
+    gameobject.mesh.set_position([new_position.x, new_position.y, new_position.z]);
+}
+
+fn position_pickup(entity: &EntityId, store: &mut recs::Ecs, time_scale: f32) {
+    let gameobject = store.get::<GameObject>(*entity).unwrap();
+    let old_position = store.get::<Position>(*entity).unwrap();
+    let new_position = Position{ x: old_position.x, y: old_position.y, z: old_position.z + gameobject.velocity * time_scale };
+    let _ = store.set::<Position>(*entity, new_position).unwrap();
+    let _ = store.set(*entity, Displacement{ x: 0.0, y: 0.0, z: gameobject.velocity * time_scale });
 
     gameobject.mesh.set_position([new_position.x, new_position.y, new_position.z]);
 }
@@ -27,19 +77,33 @@ fn position_player(entity: &EntityId, store: &mut recs::Ecs) {
     gameobject.mesh.set_position([position.x, position.y, position.z]);
 }
 
-pub fn run(mut store: &mut recs::Ecs) {
+pub fn run(mut store: &mut recs::Ecs, rhythm: &Rhythm, intensity: f32, events: &Vec<GameEvent>) {
     let component_filter = component_filter!(Position, GameObject);
     let mut entities: Vec<EntityId> = Vec::new(); 
     
     store.collect_with(&component_filter, &mut entities);
 
+    let on_beat = events.iter().any(|event| *event == GameEvent::Beat);
+    let time_scale = system::pickup::time_scale(&store, rhythm);
+    let mut player = Position{ x: 0.0, y: 0.0, z: 0.0 };
+    for entity in entities.iter() {
+        if store.get::<GameObject>(*entity).unwrap().object_type == GameObjectType::Player {
+            player = store.get::<Position>(*entity).unwrap();
+        }
+    }
+
     for entity in entities.iter() {
         let gameobject = store.get::<GameObject>(*entity).unwrap();
 
         match gameobject.object_type {
-            GameObjectType::Enemy => position_enemy(entity, &mut store), 
+            //enemies speed up and slow down with the energy of the song
+            GameObjectType::Enemy => position_enemy(entity, &mut store, rhythm, on_beat, &player, time_scale * intensity), 
             GameObjectType::Player => position_player(entity, &mut store), 
-            GameObjectType::Bullet => position_bullet(entity, &mut store),
+            //boss parts are placed around their anchor by the boss system, only the mesh has to follow
+            GameObjectType::BossPart => position_player(entity, &mut store),
+            GameObjectType::Bullet => position_bullet(entity, &mut store, 1.0),
+            GameObjectType::HostileBullet => position_bullet(entity, &mut store, time_scale),
+            GameObjectType::Pickup => position_pickup(entity, &mut store, time_scale),
         }
     }
 }
\ No newline at end of file
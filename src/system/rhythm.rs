@@ -0,0 +1,154 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//timing windows in milliseconds measured from the nearest beat
+const PERFECT_WINDOW: f32 = 50.0;
+const GREAT_WINDOW: f32 = 100.0;
+const GOOD_WINDOW: f32 = 150.0;
+
+//register a detected beat and refine the estimated time between beats
+//latency is how late the detection runs behind the music, the beat is placed back where it was heard
+pub fn beat(rhythm: &mut Rhythm, latency: f32) {
+    let heard = rhythm.now - latency;
+    let delta = heard - rhythm.last_beat;
+
+    //anything outside of 30 - 240 bpm is most likely a missed or a double detection
+    if delta > 0.25 && delta < 2.0 {
+        rhythm.interval = (rhythm.interval * 0.8) + (delta * 0.2);
+    }
+
+    rhythm.last_beat = heard;
+    rhythm.beats = rhythm.beats + 1;
+}
+
+//with a beatmap the beat grid is known, returns true on the frame the next beat of the grid is reached
+pub fn mapped_beat(rhythm: &mut Rhythm, beatmap: &track::Beatmap) -> bool {
+    rhythm.interval = 60.0 / beatmap.bpm;
+    if rhythm.now < beatmap.offset {
+        return false;
+    }
+
+    let latest = beatmap.offset + ((rhythm.now - beatmap.offset) / rhythm.interval).floor() * rhythm.interval;
+    if rhythm.beats > 0 && latest <= rhythm.last_beat {
+        return false;
+    }
+
+    rhythm.last_beat = latest;
+    rhythm.beats = rhythm.beats + 1;
+    return true;
+}
+
+//the distance in seconds to the closest beat, either the last detected one or a predicted one
+//the last beat can lie slightly ahead of now, the phase is wrapped so that still measures a distance
+pub fn nearest_beat_offset(rhythm: &Rhythm) -> f32 {
+    let phase = (rhythm.now - rhythm.last_beat).rem_euclid(rhythm.interval);
+    return phase.min(rhythm.interval - phase).abs();
+}
+
+pub fn judge(rhythm: &Rhythm) -> Judgement {
+    let offset = nearest_beat_offset(rhythm) * 1000.0;
+
+    if offset <= PERFECT_WINDOW {
+        return Judgement::Perfect;
+    }
+
+    if offset <= GREAT_WINDOW {
+        return Judgement::Great;
+    }
+
+    if offset <= GOOD_WINDOW {
+        return Judgement::Good;
+    }
+
+    return Judgement::Miss;
+}
+
+pub fn run(store: &mut recs::Ecs, events: &Vec<GameEvent>) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Accuracy), &mut entities);
+    let mut accuracy = store.get::<Accuracy>(entities[0]).unwrap();
+
+    for event in events.iter() {
+        match event {
+            GameEvent::ShotFired{ judgement } => accuracy.last = Some(*judgement),
+            _ => ()
+        }
+    }
+
+    let (text, color) = match accuracy.last {
+        Some(Judgement::Perfect) => ("PERFECT", 0xFFD700),
+        Some(Judgement::Great) => ("GREAT", 0x00FF7F),
+        Some(Judgement::Good) => ("GOOD", 0x1E90FF),
+        Some(Judgement::Miss) => ("MISS", 0x808080),
+        None => ("", 0xFFFFFF)
+    };
+
+    accuracy.ui.set_text(text);
+    accuracy.ui.set_color(color);
+    let _ = store.set(entities[0], accuracy);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rhythm(now: f32) -> Rhythm {
+        return Rhythm{ now: now, last_beat: 10.0, interval: 0.5, beats: 1 };
+    }
+
+    #[test]
+    fn judge_uses_the_timing_windows() {
+        assert_eq!(judge(&rhythm(10.03)), Judgement::Perfect);
+        assert_eq!(judge(&rhythm(10.08)), Judgement::Great);
+        assert_eq!(judge(&rhythm(10.13)), Judgement::Good);
+        assert_eq!(judge(&rhythm(10.2)), Judgement::Miss);
+    }
+
+    #[test]
+    fn judge_counts_shots_just_before_the_next_beat() {
+        assert_eq!(judge(&rhythm(10.47)), Judgement::Perfect);
+        assert_eq!(judge(&rhythm(10.42)), Judgement::Great);
+    }
+
+    #[test]
+    fn judge_predicts_beats_after_the_last_detected_one() {
+        assert_eq!(judge(&rhythm(11.51)), Judgement::Perfect);
+        assert_eq!(judge(&rhythm(12.25)), Judgement::Miss);
+    }
+
+    #[test]
+    fn judge_handles_a_last_beat_ahead_of_now() {
+        assert_eq!(judge(&rhythm(9.97)), Judgement::Perfect);
+        assert_eq!(judge(&rhythm(9.8)), Judgement::Miss);
+        assert!(nearest_beat_offset(&rhythm(9.8)) > 0.0);
+    }
+
+    #[test]
+    fn mapped_beat_follows_the_beatmap_grid() {
+        let beatmap = track::Beatmap{ bpm: 120.0, offset: 0.25 };
+        let mut rhythm = Rhythm{ now: 0.1, last_beat: 0.0, interval: 0.6, beats: 0 };
+        assert!(!mapped_beat(&mut rhythm, &beatmap));
+
+        rhythm.now = 0.3;
+        assert!(mapped_beat(&mut rhythm, &beatmap));
+        assert_eq!(rhythm.last_beat, 0.25);
+        assert_eq!(rhythm.interval, 0.5);
+
+        rhythm.now = 0.5;
+        assert!(!mapped_beat(&mut rhythm, &beatmap));
+
+        rhythm.now = 0.8;
+        assert!(mapped_beat(&mut rhythm, &beatmap));
+        assert_eq!(rhythm.last_beat, 0.75);
+        assert_eq!(rhythm.beats, 2);
+    }
+
+    #[test]
+    fn beat_ignores_double_detections_for_the_interval() {
+        let mut rhythm = rhythm(10.1);
+        beat(&mut rhythm, 0.0);
+        assert_eq!(rhythm.interval, 0.5);
+        assert_eq!(rhythm.last_beat, 10.1);
+        assert_eq!(rhythm.beats, 2);
+    }
+}
\ No newline at end of file
@@ -1,14 +1,59 @@
 use recs::{EntityId, component_filter};
 use crate::*; 
 
-pub fn run(store: &mut recs::Ecs) {
+const BOSS_HIT_POINTS: i32 = 50;
+const BOSS_DEFEATED_POINTS: i32 = 5000;
+//awarded for every life left when a track is cleared
+const CLEAR_BONUS_PER_LIFE: i32 = 1000;
+
+fn points(base: i32, judgement: Option<Judgement>) -> i32 {
+    let timing = match judgement {
+        Some(Judgement::Perfect) => 3.0,
+        Some(Judgement::Great) => 2.0,
+        Some(Judgement::Good) => 1.0,
+        Some(Judgement::Miss) => 0.5,
+        //automatic shots aren't judged
+        None => 1.0
+    };
+
+    return ((base as f32) * timing) as i32;
+}
+
+pub fn run(store: &mut recs::Ecs, events: &Vec<GameEvent>) {
     //NOTE: this method is pretty ineffecient, I should probably try something with a history in a gamestate
     let mut scores: Vec<EntityId> = Vec::new(); 
     store.collect_with(&component_filter!(Score), &mut scores);
     let mut score = store.get::<Score>(scores[0]).unwrap();
+    let combo = store.get::<Combo>(scores[0]).unwrap();
+    let multiplier = system::combo::multiplier(&combo);
+
+    for event in events.iter() {
+        match event {
+            GameEvent::EnemyDestroyed{ by: GameObjectType::Bullet, archetype, judgement, .. } => score.total = score.total + (points(archetype.points, *judgement) * multiplier),
+            GameEvent::BossHit{ judgement, .. } => score.total = score.total + (points(BOSS_HIT_POINTS, *judgement) * multiplier),
+            GameEvent::BossDefeated{ .. } => score.total = score.total + (BOSS_DEFEATED_POINTS * multiplier),
+            _ => ()
+        }
+    }
     
     let score_prefix: &str = "score: ";
     let score_string: &str = &score.total.to_string();
 
    score.ui.set_text(format!("{}{}", score_prefix, score_string)); 
+   let _ = store.set::<Score>(scores[0], score);
+}
+
+//surviving a whole track is rewarded by how much health is left, returns the bonus so it can be shown
+pub fn clear_bonus(store: &mut recs::Ecs) -> i32 {
+    let mut scores: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Score, Health), &mut scores);
+    if scores.is_empty() {
+        return 0;
+    }
+
+    let mut score = store.get::<Score>(scores[0]).unwrap();
+    let bonus = store.get::<Health>(scores[0]).unwrap().total.max(0) * CLEAR_BONUS_PER_LIFE;
+    score.total = score.total + bonus;
+    let _ = store.set::<Score>(scores[0], score);
+    return bonus;
 }
\ No newline at end of file
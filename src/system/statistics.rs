@@ -0,0 +1,99 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+
+//seconds between two samples of the score timeline
+const SAMPLE_INTERVAL: f32 = 1.0;
+
+fn judgement_index(judgement: Judgement) -> usize {
+    match judgement {
+        Judgement::Perfect => return 0,
+        Judgement::Great => return 1,
+        Judgement::Good => return 2,
+        Judgement::Miss => return 3
+    }
+}
+
+//enemies are spawned from a few places that don't emit events, so the factory reports them here
+pub fn enemy_spawned(store: &mut recs::Ecs) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Statistics), &mut entities);
+    if entities.is_empty() {
+        return;
+    }
+
+    let mut statistics = store.get::<Statistics>(entities[0]).unwrap();
+    statistics.enemies_spawned = statistics.enemies_spawned + 1;
+    let _ = store.set(entities[0], statistics);
+}
+
+pub fn run(store: &mut recs::Ecs, rhythm: &Rhythm, events: &Vec<GameEvent>) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Statistics, Score), &mut entities);
+    let mut statistics = store.get::<Statistics>(entities[0]).unwrap();
+
+    for event in events.iter() {
+        match event {
+            GameEvent::ShotFired{ judgement } => {
+                let index = judgement_index(*judgement);
+                statistics.judgements[index] = statistics.judgements[index] + 1;
+            },
+            GameEvent::ProjectileHit => statistics.projectiles_hit = statistics.projectiles_hit + 1,
+            GameEvent::ProjectileMissed => statistics.projectiles_missed = statistics.projectiles_missed + 1,
+            GameEvent::PlayerHit{ .. } => statistics.damage_taken = statistics.damage_taken + 1,
+            //enemies that crash into the player aren't destroyed by the player
+            GameEvent::EnemyDestroyed{ by: GameObjectType::Bullet, .. } => statistics.enemies_destroyed = statistics.enemies_destroyed + 1,
+            _ => ()
+        }
+    }
+
+    if rhythm.now >= statistics.next_sample {
+        statistics.timeline.push(store.get::<Score>(entities[0]).unwrap().total);
+        statistics.next_sample = statistics.next_sample + SAMPLE_INTERVAL;
+    }
+
+    let _ = store.set(entities[0], statistics);
+}
+
+pub fn timeline(store: &recs::Ecs) -> Vec<i32> {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Statistics), &mut entities);
+    if entities.is_empty() {
+        return Vec::new();
+    }
+
+    return store.get::<Statistics>(entities[0]).unwrap().timeline;
+}
+
+//accuracy is the share of projectiles that hit something, the timing of the judged shots is listed separately
+pub fn results_text(store: &recs::Ecs, title: &str) -> String {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Statistics, Score, Combo), &mut entities);
+    if entities.is_empty() {
+        return title.to_string();
+    }
+
+    let statistics = store.get::<Statistics>(entities[0]).unwrap();
+    let score = store.get::<Score>(entities[0]).unwrap();
+    let combo = store.get::<Combo>(entities[0]).unwrap();
+
+    let hits = statistics.projectiles_hit;
+    let misses = statistics.projectiles_missed;
+    let accuracy = if hits + misses > 0 { 100.0 * (hits as f32) / ((hits + misses) as f32) } else { 0.0 };
+
+    return format!(
+        "{}\n\nscore: {}\naccuracy: {:.1}%\nhits: {}  misses: {}\nperfect: {}  great: {}  good: {}  off beat: {}\nlongest combo: {}\ndamage taken: {}\nenemies destroyed: {} of {}",
+        title,
+        score.total,
+        accuracy,
+        hits,
+        misses,
+        statistics.judgements[0],
+        statistics.judgements[1],
+        statistics.judgements[2],
+        statistics.judgements[3],
+        combo.longest,
+        statistics.damage_taken,
+        statistics.enemies_destroyed,
+        statistics.enemies_spawned
+    );
+}
\ No newline at end of file
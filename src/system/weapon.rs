@@ -0,0 +1,145 @@
+use recs::{EntityId, component_filter};
+use crate::*; 
+use crate::action::Action;
+
+//seconds of holding needed for a fully charged shot and how much stronger that shot is
+const FULL_CHARGE_TIME: f32 = 1.5;
+const MAX_CHARGE: f32 = 5.0;
+//the beam covers the tunnel from the player up to where enemies spawn
+const BEAM_LENGTH: f32 = 30.0;
+//a beam pulse stays around for a few frames so it can be seen
+const BEAM_DURATION: f32 = 0.05;
+
+//the latest beat on the grid predicted from the last detected beat and the beat interval
+fn latest_beat(rhythm: &Rhythm) -> f32 {
+    return rhythm.last_beat + ((rhythm.now - rhythm.last_beat) / rhythm.interval).floor() * rhythm.interval;
+}
+
+fn fire(window: &mut three::Window, store: &mut recs::Ecs, weapon: &Weapon, powerups: &PowerUps, position: Position, charge: f32, judgement: Option<Judgement>, rhythm: &Rhythm) {
+    let stats = weapon.stats;
+
+    if stats.mode == weapon::FireMode::Beam {
+        let beam = factory::create_bullet(window, store, Position{ x: position.x, y: position.y, z: position.z - (BEAM_LENGTH / 2.0) }, Projectile{
+            direction: [0.0, 0.0, -1.0],
+            damage: stats.damage,
+            piercing: true,
+            expires: Some(rhythm.now + BEAM_DURATION),
+            hits: Vec::new()
+        }, 0.0, [0.15, 0.15, BEAM_LENGTH]);
+        if let Some(judgement) = judgement {
+            let _ = store.set(beam, judgement);
+        }
+        return;
+    }
+
+    let mut projectiles = stats.projectiles;
+    let mut spread = stats.spread;
+    if powerups.spread_shot_until > rhythm.now {
+        projectiles = projectiles + 2;
+        spread = spread.max(8.0);
+    }
+
+    for index in 0..projectiles {
+        let angle = ((index as f32) - ((projectiles - 1) as f32 / 2.0)) * spread;
+        let direction = [angle.to_radians().sin(), 0.0, -angle.to_radians().cos()];
+        let bullet = factory::create_bullet(window, store, position, Projectile{
+            direction: direction,
+            damage: ((stats.damage as f32) * charge).round() as i32,
+            piercing: false,
+            expires: None,
+            hits: Vec::new()
+        }, stats.projectile_speed, [0.1 * charge, 0.1 * charge, 0.5]);
+        if let Some(judgement) = judgement {
+            let _ = store.set(bullet, judgement);
+        }
+    }
+}
+
+pub fn run(window: &mut three::Window, store: &mut recs::Ecs, controls: &action::Controls, rhythm: &Rhythm, events: &mut Vec<GameEvent>) {
+    let mut entities: Vec<EntityId> = Vec::new(); 
+    store.collect_with(&component_filter!(Weapon, Position), &mut entities);
+    if entities.is_empty() {
+        return;
+    }
+
+    let entity = entities[0];
+    let position = store.get::<Position>(entity).unwrap();
+    let powerups = store.get::<PowerUps>(entity).unwrap();
+    let mut weapon = store.get::<Weapon>(entity).unwrap();
+
+    let weapon_actions = [Action::Weapon1, Action::Weapon2, Action::Weapon3, Action::Weapon4];
+    for (index, action) in weapon_actions.iter().enumerate() {
+        if controls.pressed(*action) && weapon.stats != weapon::ARSENAL[index] {
+            weapon.stats = weapon::ARSENAL[index];
+            weapon.charge_started = None;
+        }
+    }
+
+    if controls.pressed(Action::ToggleAutoFire) {
+        weapon.auto_fire = !weapon.auto_fire;
+    }
+
+    //rapid fire doubles the fire rate and keeps shooting while fire is held down
+    let rapid_fire = powerups.rapid_fire_until > rhythm.now;
+    let fire_rate = if rapid_fire { weapon.stats.fire_rate * 2.0 } else { weapon.stats.fire_rate };
+    let ready = rhythm.now - weapon.last_shot >= 1.0 / fire_rate;
+    let held = controls.held(Action::Fire);
+    let pressed = controls.pressed(Action::Fire);
+
+    let mut charge = 1.0;
+    //only pulling the trigger is judged, automatic shots don't count towards the timing statistics or the combo
+    let mut judged = false;
+    //auto-fire is quantized to the beat so it never lands off-beat, but it doesn't earn any timing bonus either
+    let trigger = if weapon.auto_fire {
+        latest_beat(rhythm) > weapon.last_shot
+    } else {
+        match weapon.stats.mode {
+            //every press fires, the fire rate only limits holding the trigger down with rapid fire
+            weapon::FireMode::Single => {
+                judged = pressed;
+                pressed || (rapid_fire && held && ready)
+            },
+            weapon::FireMode::Beam => {
+                judged = pressed;
+                pressed || (ready && held)
+            },
+            weapon::FireMode::Charge => {
+                if held && ready && weapon.charge_started.is_none() {
+                    weapon.charge_started = Some(rhythm.now);
+                }
+
+                match weapon.charge_started {
+                    Some(started) if !held => {
+                        charge = 1.0 + ((rhythm.now - started) / FULL_CHARGE_TIME).min(1.0) * (MAX_CHARGE - 1.0);
+                        weapon.charge_started = None;
+                        judged = true;
+                        true
+                    },
+                    _ => false
+                }
+            }
+        }
+    };
+
+    if trigger {
+        //automatic shots carry no judgement, their kills score the base points and count for the combo like any hit
+        let judgement = if judged { Some(system::rhythm::judge(rhythm)) } else { None };
+        fire(window, store, &weapon, &powerups, position, charge, judgement, rhythm);
+        if let Some(judgement) = judgement {
+            events.push(GameEvent::ShotFired{ judgement: judgement });
+        }
+        weapon.last_shot = rhythm.now;
+    }
+
+    let mut status = String::from(weapon.stats.name);
+    if let Some(started) = weapon.charge_started {
+        let progress = ((rhythm.now - started) / FULL_CHARGE_TIME).min(1.0);
+        status = format!("{} [{}{}]", status, "|".repeat((progress * 10.0) as usize), " ".repeat(10 - (progress * 10.0) as usize));
+    }
+    if weapon.auto_fire {
+        status = format!("{} (auto)", status);
+    }
+
+    weapon.ui.set_text(status);
+    let _ = store.set(entity, weapon);
+}
\ No newline at end of file
@@ -0,0 +1,318 @@
+//tracks are uncompressed wav files in a directory, the picked one is decoded up front so the analysis can read it in step with playback
+//a track can come with a beatmap, a `<name>.beatmap` file next to it with `bpm = <bpm>` and `offset = <seconds to the first beat>`
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use crate::*;
+
+const DEFAULT_DIRECTORY: &str = "./tracks";
+//size of the blocks of onset energy used to estimate the tempo
+const BPM_HOP: usize = 512;
+
+//the beats of a track are known up front, they don't have to be detected
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Beatmap {
+    pub bpm: f32,
+    pub offset: f32
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Track {
+    pub path: PathBuf,
+    pub name: String,
+    //length in seconds
+    pub duration: f32,
+    //taken from the beatmap, otherwise it is estimated in the background after the tracks are listed
+    pub bpm: Option<f32>,
+    pub beatmap: Option<Beatmap>
+}
+
+//what the chunk headers of a wav file say about its samples
+struct Header {
+    encoding: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+    //bytes of sample data
+    length: u64
+}
+
+//the whole track mixed down to mono
+#[derive(Clone, PartialEq, Debug)]
+pub struct Audio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32
+}
+
+//the track directory is picked with `--tracks <directory>`
+pub fn directory_from_args() -> String {
+    return util::argument("--tracks").unwrap_or(DEFAULT_DIRECTORY.to_string());
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    return u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+}
+
+fn is_supported(encoding: u16, bits: u16) -> bool {
+    match (encoding, bits) {
+        (1, 8) | (1, 16) | (1, 24) | (1, 32) | (3, 32) => return true,
+        _ => return false
+    }
+}
+
+//interleaved pcm or float frames are averaged over their channels
+fn to_mono(data: &[u8], encoding: u16, channels: u16, bits: u16) -> Option<Vec<f32>> {
+    let convert: fn(&[u8]) -> f32 = match (encoding, bits) {
+        (1, 8) => |bytes| (bytes[0] as f32 - 128.0) / 128.0,
+        (1, 16) => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (1, 24) => |bytes| (((bytes[2] as i8 as i32) << 16) | ((bytes[1] as i32) << 8) | (bytes[0] as i32)) as f32 / 8388608.0,
+        (1, 32) => |bytes| i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0,
+        (3, 32) => |bytes| f32::from_bits(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        _ => return None
+    };
+
+    let width = (bits / 8) as usize;
+    let channels = channels as usize;
+    if channels == 0 {
+        return None;
+    }
+
+    let samples = data.chunks_exact(width * channels).map(|frame| {
+        let sum = frame.chunks_exact(width).fold(0.0, |sum, sample| sum + convert(sample));
+        return sum / (channels as f32);
+    }).collect();
+    return Some(samples);
+}
+
+pub fn decode(path: &Path) -> Option<Audio> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+
+    //encoding, channels, sample rate and bits per sample
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(&bytes, offset + 4) as usize;
+        let start = offset + 8;
+        let end = (start + size).min(bytes.len());
+
+        if id == b"fmt " && size >= 16 {
+            format = Some((read_u16(&bytes, start), read_u16(&bytes, start + 2), read_u32(&bytes, start + 4), read_u16(&bytes, start + 14)));
+        } else if id == b"data" {
+            let (encoding, channels, sample_rate, bits) = format?;
+            let samples = to_mono(&bytes[start..end], encoding, channels, bits)?;
+            return Some(Audio{ samples: samples, sample_rate: sample_rate });
+        }
+
+        //chunks are padded to an even number of bytes
+        offset = start + size + size % 2;
+    }
+
+    return None;
+}
+
+//only the chunk headers are read, the sample data is skipped over
+fn read_header(path: &Path) -> Option<Header> {
+    let mut file = fs::File::open(path).ok()?;
+    let file_length = file.metadata().ok()?.len();
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff).ok()?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return None;
+    }
+
+    //encoding, channels, sample rate and bits per sample
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut chunk = [0u8; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = read_u32(&chunk, 4) as u64;
+        let mut skip = size + size % 2;
+
+        if &chunk[0..4] == b"fmt " && size >= 16 {
+            let mut fmt = [0u8; 16];
+            file.read_exact(&mut fmt).ok()?;
+            format = Some((read_u16(&fmt, 0), read_u16(&fmt, 2), read_u32(&fmt, 4), read_u16(&fmt, 14)));
+            skip = skip - 16;
+        } else if &chunk[0..4] == b"data" {
+            let (encoding, channels, sample_rate, bits) = format?;
+            //streamed files don't always know their length, the data then runs to the end of the file
+            let start = file.seek(SeekFrom::Current(0)).ok()?;
+            return Some(Header{ encoding: encoding, channels: channels, sample_rate: sample_rate, bits: bits, length: size.min(file_length - start) });
+        }
+
+        file.seek(SeekFrom::Current(skip as i64)).ok()?;
+    }
+
+    return None;
+}
+
+fn load_beatmap(path: &Path) -> Option<Beatmap> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut bpm: Option<f32> = None;
+    let mut offset: f32 = 0.0;
+
+    for line in contents.lines() {
+        let mut parts = line.splitn(2, '=');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name.trim(), value.trim()),
+            _ => continue
+        };
+
+        match name {
+            "bpm" => bpm = value.parse().ok(),
+            "offset" => offset = value.parse().unwrap_or(offset),
+            _ => ()
+        }
+    }
+
+    match bpm {
+        Some(bpm) if bpm > 0.0 => return Some(Beatmap{ bpm: bpm, offset: offset.max(0.0) }),
+        _ => {
+            println!("[track]: ignoring {:?}, it needs a bpm above 0.", path);
+            return None;
+        }
+    }
+}
+
+//the strongest periodicity of the rises in loudness between 60 and 180 bpm
+fn estimate_bpm(audio: &Audio) -> Option<f32> {
+    let energies: Vec<f32> = audio.samples.chunks(BPM_HOP).map(|block| {
+        return block.iter().fold(0.0, |sum, sample| sum + sample * sample) / (block.len() as f32);
+    }).collect();
+    let onsets: Vec<f32> = energies.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)).collect();
+
+    let blocks_per_second = (audio.sample_rate as f32) / (BPM_HOP as f32);
+    let min_lag = (blocks_per_second * 60.0 / 180.0).round() as usize;
+    let max_lag = (blocks_per_second * 60.0 / 60.0).round() as usize;
+    if min_lag == 0 || onsets.len() <= max_lag {
+        return None;
+    }
+
+    let mut best: Option<(usize, f32)> = None;
+    for lag in min_lag..=max_lag {
+        let correlation = onsets.iter().zip(onsets.iter().skip(lag)).fold(0.0, |sum, (a, b)| sum + a * b);
+        match best {
+            Some((_, strongest)) if strongest >= correlation => (),
+            _ => best = Some((lag, correlation))
+        }
+    }
+
+    match best {
+        Some((lag, correlation)) if correlation > 0.0 => return Some(60.0 * blocks_per_second / (lag as f32)),
+        _ => return None
+    }
+}
+
+pub fn list(directory: &str) -> Vec<Track> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            println!("[track]: unable to read {}. {:?}", directory, err);
+            return Vec::new();
+        }
+    };
+
+    let mut tracks: Vec<Track> = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let is_wav = path.extension().map(|extension| extension.to_string_lossy().to_lowercase() == "wav").unwrap_or(false);
+        if !is_wav {
+            continue;
+        }
+
+        let header = match read_header(&path) {
+            Some(header) if header.channels > 0 && header.sample_rate > 0 && is_supported(header.encoding, header.bits) => header,
+            _ => {
+                println!("[track]: skipping {:?}, only uncompressed wav files are supported.", path);
+                continue;
+            }
+        };
+
+        let beatmap_path = path.with_extension("beatmap");
+        let beatmap = if beatmap_path.is_file() { load_beatmap(&beatmap_path) } else { None };
+        let frame_size = (header.channels as u64) * (header.bits as u64 / 8);
+        tracks.push(Track{
+            name: path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default(),
+            duration: ((header.length / frame_size) as f32) / (header.sample_rate as f32),
+            bpm: beatmap.map(|beatmap| beatmap.bpm),
+            beatmap: beatmap,
+            path: path
+        });
+    }
+
+    tracks.sort_by(|a, b| a.name.cmp(&b.name));
+    return tracks;
+}
+
+//decoding every track takes a while, so the tempos of the tracks without a beatmap are estimated one by one on another thread
+pub fn estimate_in_background(tracks: &Vec<Track>) -> Receiver<(PathBuf, Option<f32>)> {
+    let (sender, receiver) = channel();
+    let paths: Vec<PathBuf> = tracks.iter().filter(|track| track.bpm.is_none()).map(|track| track.path.clone()).collect();
+
+    thread::spawn(move || {
+        for path in paths {
+            let bpm = decode(&path).and_then(|audio| estimate_bpm(&audio));
+            //the game has closed
+            if sender.send((path, bpm)).is_err() {
+                return;
+            }
+        }
+    });
+
+    return receiver;
+}
+
+//picks up the tempos that were estimated since the last frame
+pub fn receive_estimates(tracks: &mut Vec<Track>, receiver: &Receiver<(PathBuf, Option<f32>)>) {
+    for (path, bpm) in receiver.try_iter() {
+        match tracks.iter_mut().find(|track| track.path == path) {
+            Some(track) if track.bpm.is_none() => track.bpm = bpm,
+            _ => ()
+        }
+    }
+}
+
+//decodes a picked track, its tempo is estimated now if it isn't known yet
+pub fn load(track: &mut Track) -> Option<Audio> {
+    let audio = decode(&track.path);
+    match audio {
+        Some(ref audio) if track.bpm.is_none() => track.bpm = estimate_bpm(audio),
+        Some(_) => (),
+        None => println!("[track]: unable to decode {:?}.", track.path)
+    }
+
+    return audio;
+}
+
+pub fn describe(track: &Track) -> String {
+    let minutes = (track.duration / 60.0) as i32;
+    let seconds = (track.duration as i32) % 60;
+    let bpm = match track.bpm {
+        Some(bpm) => format!("{:.0} bpm", bpm),
+        None => "? bpm".to_string()
+    };
+
+    let beatmap = if track.beatmap.is_some() { "  beatmap" } else { "" };
+
+    return format!("{}  {}:{:02}  {}{}", track.name, minutes, seconds, bpm, beatmap);
+}
+
+//the block of samples that is playing at the given time, none once the track is over
+pub fn buffer_at(audio: &Audio, time: f32, length: usize) -> Option<&[f32]> {
+    let start = (time.max(0.0) * (audio.sample_rate as f32)) as usize;
+    if start + length > audio.samples.len() {
+        return None;
+    }
+
+    return Some(&audio.samples[start..start + length]);
+}
\ No newline at end of file
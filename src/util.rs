@@ -11,9 +11,26 @@ pub fn remove_entity(entity: EntityId, store: &mut recs::Ecs, window: &mut three
     }
 }
 
+pub fn despawn(entity: EntityId, commands: &mut super::CommandBuffer) {
+    if !commands.despawn.contains(&entity) {
+        commands.despawn.push(entity);
+    }
+}
+
+pub fn is_despawning(entity: EntityId, commands: &super::CommandBuffer) -> bool {
+    return commands.despawn.contains(&entity);
+}
+
 pub fn polar_to_cartesian(radius: f32, angle: f32) -> [f32; 2] {
     //angles are converted from degrees to radians because rust calculates sine functions with radians 
     let x = radius * angle.to_radians().cos();
     let y = radius * angle.to_radians().sin();
     return [x, y];
 }
+
+//the value following a command line flag, `--tracks ./music` gives `./music` for `--tracks`
+pub fn argument(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let position = args.iter().position(|arg| arg == flag)?;
+    return args.get(position + 1).cloned();
+}
\ No newline at end of file
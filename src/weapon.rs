@@ -0,0 +1,67 @@
+//weapons are plain data like the enemy archetypes, the player switches between them with the number keys
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FireMode {
+    //a volley of projectiles per trigger pull
+    Single,
+    //a piercing beam along the length of the tunnel for as long as the trigger is held
+    Beam,
+    //hold to charge, a bigger and stronger projectile leaves on release
+    Charge
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WeaponStats {
+    pub name: &'static str,
+    pub mode: FireMode,
+    //shots per second while the trigger is held down, a single press always fires
+    pub fire_rate: f32,
+    pub projectiles: i32,
+    //angle between projectiles in degrees
+    pub spread: f32,
+    pub projectile_speed: f32,
+    pub damage: i32
+}
+
+pub const BLASTER: WeaponStats = WeaponStats {
+    name: "blaster",
+    mode: FireMode::Single,
+    fire_rate: 8.0,
+    projectiles: 1,
+    spread: 0.0,
+    projectile_speed: 0.25,
+    damage: 1
+};
+
+pub const SPREAD: WeaponStats = WeaponStats {
+    name: "spread",
+    mode: FireMode::Single,
+    fire_rate: 4.0,
+    projectiles: 5,
+    spread: 8.0,
+    projectile_speed: 0.22,
+    damage: 1
+};
+
+pub const LASER: WeaponStats = WeaponStats {
+    name: "laser",
+    mode: FireMode::Beam,
+    fire_rate: 10.0,
+    projectiles: 1,
+    spread: 0.0,
+    projectile_speed: 0.0,
+    damage: 1
+};
+
+pub const CHARGED: WeaponStats = WeaponStats {
+    name: "charged",
+    mode: FireMode::Charge,
+    fire_rate: 2.0,
+    projectiles: 1,
+    spread: 0.0,
+    projectile_speed: 0.35,
+    damage: 1
+};
+
+//in the order of the number keys
+pub const ARSENAL: [&'static WeaponStats; 4] = [&BLASTER, &SPREAD, &LASER, &CHARGED];
\ No newline at end of file